@@ -0,0 +1,74 @@
+//! Compares `Pattern::captures()` with `Pattern::captures_into()`
+//!
+//! Run with `cargo bench --bench captures`, the number of iterations can be
+//! passed as an argument.
+extern crate capturing_glob;
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use capturing_glob::{Pattern, CaptureSpans};
+
+
+fn keys() -> Vec<String> {
+    let mut keys = Vec::new();
+    for shard in 0..64 {
+        for id in 0..32 {
+            keys.push(format!("data/shard-{:02}/{:04}/object-{}.parquet",
+                              shard, id * 37, id));
+            keys.push(format!("logs/{:02}/{}.json", shard, id));
+        }
+    }
+    keys
+}
+
+// Returns the time taken, the checksum and the number of matches
+fn run<F>(keys: &[String], iterations: usize, mut f: F)
+    -> (Duration, usize, usize)
+    where F: FnMut(&str) -> Option<usize>
+{
+    let start = Instant::now();
+    let mut total = 0;
+    let mut matches = 0;
+    for _ in 0..iterations {
+        for key in keys {
+            if let Some(value) = f(key) {
+                total += value;
+                matches += 1;
+            }
+        }
+    }
+    (start.elapsed(), total, matches)
+}
+
+fn report(name: &str, (elapsed, total, matches): (Duration, usize, usize)) {
+    let nanos = elapsed.as_secs() * 1_000_000_000
+        + u64::from(elapsed.subsec_nanos());
+    println!("{:<16} {:>10.1} ns/match  (checksum {})",
+             name, nanos as f64 / matches as f64, total);
+}
+
+fn main() {
+    let iterations = env::args().skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(200);
+    let keys = keys();
+    let pattern = Pattern::new("data/shard-(*)/(*)/(*).parquet").unwrap();
+
+    let captures = run(&keys, iterations, |key| {
+        pattern.captures(key)
+            .map(|entry| entry.group(3).unwrap().len())
+    });
+    report("captures", captures);
+
+    let mut spans = CaptureSpans::new();
+    let captures_into = run(&keys, iterations, |key| {
+        if pattern.captures_into(key, &mut spans) {
+            Some(spans.group(key, 3).unwrap().len())
+        } else {
+            None
+        }
+    });
+    report("captures_into", captures_into);
+}
@@ -0,0 +1,202 @@
+//! A derive macro for the `FromGlob` trait of the `capturing-glob` crate
+//!
+//! The pattern is given by the `#[glob("...")]` attribute and is checked at
+//! compile time. Named fields are filled with the capture groups of the same
+//! name, fields of tuple structs with the groups in order. Either can be
+//! overridden by `#[glob(group = N)]` or `#[glob(name = "...")]` on the field.
+//! Each field is parsed with `Entry::parse()`, so any `FromStr` type works.
+//!
+//! # Examples
+//!
+//! ```rust
+//! extern crate capturing_glob;
+//! #[macro_use] extern crate capturing_glob_derive;
+//!
+//! use capturing_glob::FromGlob;
+//!
+//! #[derive(FromGlob)]
+//! #[glob("content/(?<lang>*)/(?<page>*).md")]
+//! struct Page {
+//!     lang: String,
+//!     page: String,
+//! }
+//!
+//! # fn main() {
+//! for page in Page::glob().unwrap() {
+//!     let page = page.unwrap();
+//!     println!("{}: {}", page.lang, page.page);
+//! }
+//! # }
+//! ```
+//!
+//! A group that doesn't exist in the pattern is a compile error:
+//!
+//! ```rust,compile_fail
+//! # #[macro_use] extern crate capturing_glob_derive;
+//! #[derive(FromGlob)]
+//! #[glob("content/(?<lang>*)/(*).md")]
+//! struct Page {
+//!     lang: String,
+//!     page: String,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! And so is an invalid pattern:
+//!
+//! ```rust,compile_fail
+//! # #[macro_use] extern crate capturing_glob_derive;
+//! #[derive(FromGlob)]
+//! #[glob("content/(*.md")]
+//! struct Page(String);
+//! # fn main() {}
+//! ```
+extern crate capturing_glob;
+extern crate proc_macro;
+extern crate proc_macro2;
+extern crate quote;
+extern crate syn;
+
+use capturing_glob::Pattern;
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Error, Fields, LitInt, LitStr};
+
+
+/// Implement `FromGlob` for a struct, see the crate docs
+#[proc_macro_derive(FromGlob, attributes(glob))]
+pub fn derive_from_glob(input: TokenStream) -> TokenStream {
+    syn::parse::<DeriveInput>(input)
+        .and_then(|input| expand(&input))
+        .unwrap_or_else(|e| compile_error(&e))
+        .into()
+}
+
+// Unlike `Error::to_compile_error()`, this doesn't refer to `::core`, which
+// is not in scope in the crates of 2015 edition
+fn compile_error(error: &Error) -> TokenStream2 {
+    let msg = error.to_string();
+    quote_spanned! { error.span() => compile_error!(#msg); }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => &data.fields,
+        _ => return Err(Error::new_spanned(input,
+            "FromGlob can only be derived for structs")),
+    };
+    let literal = pattern_attr(input)?;
+    let pattern = Pattern::new(&literal.value()).map_err(|e| {
+        Error::new(literal.span(), format!(
+            "invalid glob pattern near position {}: {}", e.pos, e.msg))
+    })?;
+
+    let mut values = Vec::new();
+    for (idx, field) in fields.iter().enumerate() {
+        let group = match field_attr(field)? {
+            Some(FieldGroup::Number(n, span)) => {
+                if n == 0 || n > pattern.group_count() {
+                    return Err(Error::new(span, format!(
+                        "no capture group {} in the pattern", n)));
+                }
+                n
+            }
+            Some(FieldGroup::Name(name, span)) => {
+                pattern.group_index(&name).ok_or_else(|| {
+                    Error::new(span, format!(
+                        "no capture group named {:?} in the pattern", name))
+                })?
+            }
+            None => match field.ident {
+                Some(ref ident) => {
+                    let name = ident.to_string();
+                    let name = name.trim_start_matches("r#");
+                    pattern.group_index(name).ok_or_else(|| {
+                        Error::new_spanned(ident, format!(
+                            "no capture group named {:?} in the pattern, \
+                             add it as `(?<{}>...)` or use \
+                             `#[glob(group = N)]`", name, name))
+                    })?
+                }
+                None => {
+                    if idx >= pattern.group_count() {
+                        return Err(Error::new_spanned(field, format!(
+                            "no capture group {} in the pattern", idx + 1)));
+                    }
+                    idx + 1
+                }
+            },
+        };
+        values.push(quote_spanned! { field.ty.span() =>
+            __entry.parse(#group)?
+        });
+    }
+
+    let name = &input.ident;
+    let body = match *fields {
+        Fields::Named(_) => {
+            let idents = fields.iter().map(|f| &f.ident);
+            quote! { #name { #(#idents: #values),* } }
+        }
+        Fields::Unnamed(_) => quote! { #name(#(#values),*) },
+        Fields::Unit => quote! { #name },
+    };
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::capturing_glob::FromGlob
+            for #name #ty_generics #where_clause
+        {
+            const PATTERN: &'static str = #literal;
+            fn from_entry(__entry: &::capturing_glob::Entry)
+                -> ::std::result::Result<Self, ::capturing_glob::CaptureError>
+            {
+                ::std::result::Result::Ok(#body)
+            }
+        }
+    })
+}
+
+enum FieldGroup {
+    Number(usize, proc_macro2::Span),
+    Name(String, proc_macro2::Span),
+}
+
+fn pattern_attr(input: &DeriveInput) -> Result<LitStr, Error> {
+    let mut result = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("glob")) {
+        if result.is_some() {
+            return Err(Error::new_spanned(attr,
+                "duplicate `#[glob(...)]` attribute"));
+        }
+        result = Some(attr.parse_args::<LitStr>()?);
+    }
+    result.ok_or_else(|| Error::new_spanned(&input.ident,
+        "the pattern is missing, add `#[glob(\"...\")]` to the struct"))
+}
+
+fn field_attr(field: &syn::Field) -> Result<Option<FieldGroup>, Error> {
+    let mut result = None;
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("glob")) {
+        attr.parse_nested_meta(|meta| {
+            if result.is_some() {
+                return Err(meta.error("the group is already specified"));
+            }
+            if meta.path.is_ident("group") {
+                let n = meta.value()?.parse::<LitInt>()?;
+                result = Some(FieldGroup::Number(n.base10_parse()?,
+                                                 n.span()));
+            } else if meta.path.is_ident("name") {
+                let name = meta.value()?.parse::<LitStr>()?;
+                result = Some(FieldGroup::Name(name.value(), name.span()));
+            } else {
+                return Err(meta.error(
+                    "expected `group = N` or `name = \"...\"`"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(result)
+}
@@ -0,0 +1,89 @@
+extern crate capturing_glob;
+#[macro_use] extern crate capturing_glob_derive;
+
+use std::path::Path;
+
+use capturing_glob::{FromGlob, FromGlobError, Pattern};
+
+
+#[derive(FromGlob, Debug, PartialEq)]
+#[glob("content/(?<lang>*)/(?<page>*).md")]
+struct Page {
+    lang: String,
+    page: String,
+}
+
+#[derive(FromGlob, Debug, PartialEq)]
+#[glob("v(*).(*)/(?<name>*)")]
+struct Release {
+    #[glob(group = 2)]
+    minor: u32,
+    #[glob(group = 1)]
+    major: u32,
+    #[glob(name = "name")]
+    r#type: String,
+}
+
+#[derive(FromGlob, Debug, PartialEq)]
+#[glob("(*)-(*)")]
+struct Pair(String, u16);
+
+#[derive(FromGlob, Debug, PartialEq)]
+#[glob("tests/(*).rs")]
+struct Test(String);
+
+fn from<T: FromGlob>(path: &str) -> T {
+    let pattern = Pattern::new(T::PATTERN).unwrap();
+    T::from_entry(&pattern.captures(path).unwrap()).unwrap()
+}
+
+#[test]
+fn named_fields() {
+    assert_eq!(from::<Page>("content/en/index.md"), Page {
+        lang: "en".into(),
+        page: "index".into(),
+    });
+    assert_eq!(Page::PATTERN, "content/(?<lang>*)/(?<page>*).md");
+}
+
+#[test]
+fn field_attributes() {
+    assert_eq!(from::<Release>("v1.2/beta"), Release {
+        major: 1,
+        minor: 2,
+        r#type: "beta".into(),
+    });
+}
+
+#[test]
+fn tuple_struct() {
+    assert_eq!(from::<Pair>("port-8080"), Pair("port".into(), 8080));
+    let pattern = Pattern::new(Pair::PATTERN).unwrap();
+    let error = Pair::from_entry(&pattern.captures("a-b").unwrap())
+        .unwrap_err();
+    assert_eq!(error.group(), 2);
+}
+
+#[test]
+fn glob() {
+    let tests = Test::glob().unwrap()
+        .collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(tests, vec![Test("derive".into())]);
+}
+
+#[test]
+fn capture_error() {
+    assert!(Path::new("tests/derive.rs").exists());
+    #[derive(FromGlob)]
+    #[glob("tests/(*).rs")]
+    struct Number(u32);
+    match Number::glob().unwrap().next() {
+        Some(Err(FromGlobError::Capture(path, e))) => {
+            assert_eq!(path, Path::new("tests/derive.rs"));
+            assert_eq!(e.group(), 1);
+        }
+        Some(Ok(Number(n))) => panic!("unexpected number {}", n),
+        Some(Err(e)) => panic!("unexpected error {}", e),
+        None => panic!("no entries"),
+    }
+}
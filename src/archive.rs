@@ -0,0 +1,224 @@
+//! Globbing inside of archives, enabled by the `tar` and `zip` features
+//!
+//! Archives are indexed once when opened, the index implements
+//! `FileSystem` so it can be passed to `glob_in()`. Members matched by the
+//! pattern are then read with `open_member()`.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "tar")] use std::io::{BufReader, Seek, SeekFrom};
+#[cfg(feature = "tar")] use flate2::read::GzDecoder;
+#[cfg(feature = "tar")] use tar;
+#[cfg(feature = "zip")] use zip;
+
+use filesystem::{normalize, FileSystem, MemoryFs};
+
+
+// Directory tree of the members along with the data needed to read them
+#[derive(Debug, Clone)]
+struct Index<M> {
+    tree: MemoryFs,
+    members: BTreeMap<PathBuf, M>,
+}
+
+impl<M> Index<M> {
+    fn new() -> Index<M> {
+        Index {
+            tree: MemoryFs::new(),
+            members: BTreeMap::new(),
+        }
+    }
+    fn add_dir(&mut self, path: &Path) {
+        self.tree.add_dir(path);
+    }
+    fn add_file(&mut self, path: &Path, member: M) {
+        self.tree.add_file(path);
+        self.members.insert(normalize(path), member);
+    }
+    fn get(&self, path: &Path) -> io::Result<&M> {
+        self.members.get(&normalize(path)).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound,
+                format!("no member {:?} in the archive", path))
+        })
+    }
+}
+
+// Members with absolute names or `..` would be found outside of the
+// archive, `enclosed_name()` of the zip members skips them the same way
+#[cfg(feature = "tar")]
+fn enclosed_name(path: &Path) -> Option<&Path> {
+    use std::path::Component::{Normal, CurDir};
+    if path.components().all(|c| matches!(c, Normal(_) | CurDir)) {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// An index of a tar archive, optionally gzip-compressed
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::io::Read;
+/// use capturing_glob::{glob_in, TarArchive, MatchOptions};
+///
+/// let archive = TarArchive::open("release.tar.gz").unwrap();
+/// let options = MatchOptions::new();
+/// for entry in glob_in(&archive, "(*)/bin/(*)", &options).unwrap() {
+///     let entry = entry.unwrap();
+///     let mut data = Vec::new();
+///     archive.open_member(entry.path()).unwrap()
+///         .read_to_end(&mut data).unwrap();
+///     println!("{:?} of {:?}: {} bytes",
+///              entry.group(2).unwrap(), entry.group(1).unwrap(), data.len());
+/// }
+/// ```
+#[cfg(feature = "tar")]
+#[derive(Debug, Clone)]
+pub struct TarArchive {
+    path: PathBuf,
+    gzip: bool,
+    // offset of the data and its size
+    index: Index<(u64, u64)>,
+}
+
+#[cfg(feature = "tar")]
+impl TarArchive {
+    /// Read the index of the archive, gzip compression is detected by the
+    /// contents of the file
+    ///
+    /// Members with unsafe names, i.e. absolute ones or the ones containing
+    /// `..`, are skipped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<TarArchive> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let mut magic = [0u8; 2];
+        let gzip = file.read(&mut magic)? == 2 && magic == [0x1f, 0x8b];
+        file.seek(SeekFrom::Start(0))?;
+        let index = if gzip {
+            TarArchive::index(GzDecoder::new(BufReader::new(file)))?
+        } else {
+            TarArchive::index(BufReader::new(file))?
+        };
+        Ok(TarArchive { path, gzip, index })
+    }
+
+    fn index<R: Read>(reader: R) -> io::Result<Index<(u64, u64)>> {
+        let mut index = Index::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let entry = entry?;
+            let path = entry.path()?;
+            let path = match enclosed_name(&path) {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+            if entry.header().entry_type().is_dir() {
+                index.add_dir(&path);
+            } else {
+                index.add_file(&path,
+                    (entry.raw_file_position(), entry.size()));
+            }
+        }
+        Ok(index)
+    }
+
+    /// Return the reader of the contents of the member at `path`, as
+    /// returned by the glob
+    ///
+    /// For compressed archives the data preceding the member has to be
+    /// decompressed first.
+    pub fn open_member(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        let (offset, size) = *self.index.get(path)?;
+        let mut file = File::open(&self.path)?;
+        if self.gzip {
+            let mut reader = GzDecoder::new(BufReader::new(file));
+            io::copy(&mut (&mut reader).take(offset), &mut io::sink())?;
+            Ok(Box::new(reader.take(size)))
+        } else {
+            file.seek(SeekFrom::Start(offset))?;
+            Ok(Box::new(file.take(size)))
+        }
+    }
+}
+
+#[cfg(feature = "tar")]
+impl FileSystem for TarArchive {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        self.index.tree.read_dir(path)
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        self.index.tree.is_dir(path)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        self.index.tree.exists(path)
+    }
+}
+
+/// An index of a zip archive
+///
+/// Works the same way as `TarArchive`.
+#[cfg(feature = "zip")]
+#[derive(Debug, Clone)]
+pub struct ZipArchive {
+    path: PathBuf,
+    // number of the file in the archive
+    index: Index<usize>,
+}
+
+#[cfg(feature = "zip")]
+impl ZipArchive {
+    /// Read the index of the archive
+    ///
+    /// Members with unsafe names, i.e. absolute ones or the ones containing
+    /// `..`, are skipped.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<ZipArchive> {
+        let path = path.as_ref().to_path_buf();
+        let mut archive = zip::ZipArchive::new(File::open(&path)?)?;
+        let mut index = Index::new();
+        for n in 0..archive.len() {
+            let file = archive.by_index(n)?;
+            let name = match file.enclosed_name() {
+                Some(name) => name.to_path_buf(),
+                None => continue,
+            };
+            if file.is_dir() {
+                index.add_dir(&name);
+            } else {
+                index.add_file(&name, n);
+            }
+        }
+        Ok(ZipArchive { path, index })
+    }
+
+    /// Return the reader of the contents of the member at `path`, as
+    /// returned by the glob
+    ///
+    /// The member is decompressed into memory.
+    pub fn open_member(&self, path: &Path) -> io::Result<Box<dyn Read>> {
+        let n = *self.index.get(path)?;
+        let mut archive = zip::ZipArchive::new(File::open(&self.path)?)?;
+        let mut file = archive.by_index(n)?;
+        let mut data = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut data)?;
+        Ok(Box::new(io::Cursor::new(data)))
+    }
+}
+
+#[cfg(feature = "zip")]
+impl FileSystem for ZipArchive {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        self.index.tree.read_dir(path)
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        self.index.tree.is_dir(path)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        self.index.tree.exists(path)
+    }
+}
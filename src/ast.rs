@@ -0,0 +1,549 @@
+//! Token-level representation of patterns
+//!
+//! `Pattern::tokens()` returns the tokens of a compiled pattern and
+//! `Pattern::from_tokens(..)` builds a pattern from them, so tools can
+//! inspect and rewrite patterns without dealing with the pattern syntax
+//! and escaping. `Display` of a `Pattern` prints its tokens in the
+//! canonical syntax.
+//!
+//! ```rust
+//! use capturing_glob::Pattern;
+//! use capturing_glob::ast::Token;
+//!
+//! let pattern = Pattern::from_tokens(&[
+//!     Token::Literal("logs/".into()),
+//!     Token::Group {
+//!         index: 1,
+//!         name: Some("date".into()),
+//!         tokens: vec![Token::AnySequence],
+//!     },
+//!     Token::Literal("[1].txt".into()),
+//! ]).unwrap();
+//! assert_eq!(pattern.to_string(), "logs/(?<date>*)[[]1].txt");
+//! assert!(pattern.matches("logs/2018-01-01[1].txt"));
+//! ```
+use std::fmt::Write;
+
+use {PatternToken, CharSpecifier, PatternError};
+
+/// A single element of a pattern
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum Token {
+    /// Text matched literally (`abc`)
+    Literal(String),
+    /// Any single character (`?`)
+    AnyChar,
+    /// Any sequence of characters (`*`)
+    AnySequence,
+    /// Any number of path components (`**`)
+    ///
+    /// Must form a whole path component. The path separator that follows
+    /// it is implied, i.e. `**/a` is `[RecursiveSequence, Literal("a")]`.
+    RecursiveSequence,
+    /// Any character in the set (`[abc]`), or any character not in the set
+    /// if `negated` is true (`[!abc]`)
+    Class {
+        /// Whether this is the `[!...]` form
+        negated: bool,
+        /// The characters, ranges and classes in the set
+        items: Vec<ClassItem>,
+    },
+    /// Any decimal number within the range, inclusive (`{1..20}`), zero
+    /// padded to `width` characters if it's non-zero (`{00..63}`)
+    NumRange {
+        /// The number written first
+        first: u64,
+        /// The number written last
+        last: u64,
+        /// The width to pad the numbers to, zero if they are not padded
+        width: usize,
+    },
+    /// Any of the alternatives (`{a,b}`)
+    Alternatives(Vec<Vec<Token>>),
+    /// An extended glob operator (`@(a|b)`), which is also a capture group
+    ExtGlob {
+        /// The operator
+        kind: ExtGlobKind,
+        /// The 1-based index of the group
+        index: usize,
+        /// The alternatives
+        alternatives: Vec<Vec<Token>>,
+    },
+    /// A capture group (`(...)` or `(?<name>...)`)
+    Group {
+        /// The 1-based index of the group, as in `Entry::group(..)`
+        index: usize,
+        /// The name of the group, if any
+        name: Option<String>,
+        /// The tokens inside of the group
+        tokens: Vec<Token>,
+    },
+}
+
+/// An element of the character set in `Token::Class`
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum ClassItem {
+    /// A single character
+    Char(char),
+    /// A range of characters, inclusive (`a-z`)
+    Range(char, char),
+    /// A POSIX character class (`[:alpha:]`)
+    Posix(CharClass),
+}
+
+/// A POSIX character class
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+#[allow(missing_docs)]
+pub enum CharClass {
+    Alnum,
+    Alpha,
+    Blank,
+    Cntrl,
+    Digit,
+    Graph,
+    Lower,
+    Print,
+    Punct,
+    Space,
+    Upper,
+    Xdigit,
+}
+
+/// The kind of an extended glob operator
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum ExtGlobKind {
+    /// `?(...)`, zero or one occurrence
+    ZeroOrOne,
+    /// `*(...)`, zero or more occurrences
+    ZeroOrMore,
+    /// `+(...)`, one or more occurrences
+    OneOrMore,
+    /// `@(...)`, exactly one occurrence
+    ExactlyOne,
+    /// `!(...)`, anything except the alternatives
+    Not,
+}
+
+// Characters escaped everywhere, the parser treats a single escaped
+// character in brackets as a literal
+const ESCAPED: &[char] = &['?', '*', '[', '(', ')', '{'];
+
+/// Converts the tokens of a compiled pattern, where `names` are indexed by
+/// 0-based group number
+pub(crate) fn from_internal(tokens: &[PatternToken], names: &[Option<String>])
+    -> Vec<Token>
+{
+    use PatternToken::*;
+
+    // the groups being collected along with their index and name
+    let mut stack = vec![(0, None, Vec::new())];
+    for token in tokens {
+        let converted = match *token {
+            Char(c) => {
+                let current = &mut stack.last_mut().unwrap().2;
+                if let Some(&mut Token::Literal(ref mut text)) = current.last_mut() {
+                    text.push(c);
+                    continue;
+                }
+                Token::Literal(c.to_string())
+            }
+            AnyChar => Token::AnyChar,
+            AnySequence => Token::AnySequence,
+            AnyRecursiveSequence => Token::RecursiveSequence,
+            AnyWithin(ref specs) => Token::Class {
+                negated: false,
+                items: specs.iter().map(class_item).collect(),
+            },
+            AnyExcept(ref specs) => Token::Class {
+                negated: true,
+                items: specs.iter().map(class_item).collect(),
+            },
+            NumRange(first, last, width) => {
+                Token::NumRange { first, last, width }
+            }
+            ExtGlob(kind, ref alternatives, capture) => {
+                let alternatives = alternatives.iter()
+                    .map(|alt| from_internal(alt, names))
+                    .collect();
+                match capture {
+                    Some(n) => Token::ExtGlob { kind, index: n + 1, alternatives },
+                    None => Token::Alternatives(alternatives),
+                }
+            }
+            StartCapture(n, _) => {
+                let name = names.get(n).and_then(|name| name.clone());
+                stack.push((n + 1, name, Vec::new()));
+                continue;
+            }
+            EndCapture(..) => {
+                let (index, name, tokens) = stack.pop().unwrap();
+                Token::Group { index, name, tokens }
+            }
+        };
+        stack.last_mut().unwrap().2.push(converted);
+    }
+    debug_assert_eq!(stack.len(), 1);
+    stack.pop().unwrap().2
+}
+
+fn class_item(spec: &CharSpecifier) -> ClassItem {
+    match *spec {
+        CharSpecifier::SingleChar(c) => ClassItem::Char(c),
+        CharSpecifier::CharRange(a, b) => ClassItem::Range(a, b),
+        CharSpecifier::Class(class) => ClassItem::Posix(class),
+    }
+}
+
+/// Returns true if the tokens contain extended glob operators, so the
+/// rendered pattern must be compiled with `PatternOptions::extglob`
+pub(crate) fn has_extglob(tokens: &[Token]) -> bool {
+    tokens.iter().any(|token| match *token {
+        Token::ExtGlob { .. } => true,
+        Token::Alternatives(ref alternatives) => {
+            alternatives.iter().any(|alt| has_extglob(alt))
+        }
+        Token::Group { ref tokens, .. } => has_extglob(tokens),
+        _ => false,
+    })
+}
+
+/// Writes the tokens in the canonical pattern syntax
+pub(crate) fn render(tokens: &[Token]) -> Result<String, PatternError> {
+    let mut writer = Writer {
+        out: String::new(),
+        extglob: has_extglob(tokens),
+        separator_pending: false,
+    };
+    writer.sequence(tokens, &[])?;
+    Ok(writer.out)
+}
+
+struct Writer {
+    out: String,
+    extglob: bool,
+    // whether `**` was written and a separator must follow it
+    separator_pending: bool,
+}
+
+impl Writer {
+    fn invalid(&self, msg: &'static str) -> PatternError {
+        PatternError {
+            pos: self.out.chars().count(),
+            msg,
+        }
+    }
+
+    fn start_token(&mut self) {
+        if self.separator_pending {
+            self.out.push('/');
+            self.separator_pending = false;
+        }
+    }
+
+    fn sequence(&mut self, tokens: &[Token], special: &[char])
+        -> Result<(), PatternError>
+    {
+        for token in tokens {
+            let opens_paren = matches!(*token,
+                Token::ExtGlob { .. } | Token::Group { .. });
+            if opens_paren && self.extglob && !self.separator_pending &&
+                self.out.ends_with(|c| "?*+@!".contains(c))
+            {
+                // otherwise the character and the paren would form an
+                // extended glob operator, so separate them by empty braces
+                self.out.push_str("{,}");
+            }
+            self.token(token, special)?;
+        }
+        Ok(())
+    }
+
+    fn token(&mut self, token: &Token, special: &[char])
+        -> Result<(), PatternError>
+    {
+        match *token {
+            Token::Literal(ref text) => {
+                if !text.is_empty() {
+                    self.start_token();
+                }
+                for c in text.chars() {
+                    if ESCAPED.contains(&c) || special.contains(&c) {
+                        write!(self.out, "[{}]", c).unwrap();
+                    } else {
+                        self.out.push(c);
+                    }
+                }
+            }
+            Token::AnyChar => {
+                self.start_token();
+                self.out.push('?');
+            }
+            Token::AnySequence => {
+                self.start_token();
+                self.out.push('*');
+            }
+            Token::RecursiveSequence => {
+                self.start_token();
+                self.out.push_str("**");
+                self.separator_pending = true;
+            }
+            Token::Class { negated, ref items } => {
+                self.start_token();
+                self.class(negated, items)?;
+            }
+            Token::NumRange { first, last, width } => {
+                self.start_token();
+                write!(self.out, "{{{:0w$}..{:0w$}}}", first, last, w = width)
+                    .unwrap();
+            }
+            Token::Alternatives(ref alternatives) => {
+                if alternatives.is_empty() {
+                    return Err(self.invalid("no alternatives in braces"));
+                }
+                if alternatives.len() == 1 {
+                    // braces with a single alternative are not special
+                    return self.sequence(&alternatives[0], special);
+                }
+                self.start_token();
+                self.out.push('{');
+                for (idx, alt) in alternatives.iter().enumerate() {
+                    if idx > 0 {
+                        self.out.push(',');
+                    }
+                    self.sequence(alt, &[',', '|', '}'])?;
+                }
+                self.out.push('}');
+            }
+            Token::ExtGlob { kind, ref alternatives, .. } => {
+                if alternatives.is_empty() {
+                    return Err(self.invalid(
+                        "no alternatives in extended glob operator"));
+                }
+                self.start_token();
+                self.out.push(kind.to_char());
+                self.out.push('(');
+                for (idx, alt) in alternatives.iter().enumerate() {
+                    if idx > 0 {
+                        self.out.push('|');
+                    }
+                    self.sequence(alt, &[',', '|', '}'])?;
+                }
+                self.out.push(')');
+            }
+            Token::Group { ref name, ref tokens, .. } => {
+                self.start_token();
+                self.out.push('(');
+                if let Some(ref name) = *name {
+                    if !is_group_name(name) {
+                        return Err(self.invalid("invalid group name"));
+                    }
+                    write!(self.out, "?<{}>", name).unwrap();
+                }
+                self.sequence(tokens, special)?;
+                self.out.push(')');
+            }
+        }
+        Ok(())
+    }
+
+    fn class(&mut self, negated: bool, items: &[ClassItem])
+        -> Result<(), PatternError>
+    {
+        if items.is_empty() {
+            return Err(self.invalid("empty character set"));
+        }
+        // order the items so that none of them is interpreted specially:
+        // `]` goes first, `!` is never first, and `[` and `-` go last
+        let mut singles = Vec::new();
+        let mut rest = Vec::new();
+        for item in items {
+            match *item {
+                ClassItem::Char(c) => singles.push(c),
+                ClassItem::Range(a, b) => rest.push(format!("{}-{}", a, b)),
+                ClassItem::Posix(class) => {
+                    rest.insert(0, format!("[:{}:]", class.name()));
+                }
+            }
+        }
+        let mut ordered = String::new();
+        if singles.contains(&']') {
+            ordered.push(']');
+        }
+        for part in &rest {
+            ordered.push_str(part);
+        }
+        for &c in &singles {
+            if !"]!:[-".contains(c) {
+                ordered.push(c);
+            }
+        }
+        for c in "!:[-".chars() {
+            if singles.contains(&c) {
+                ordered.push(c);
+            }
+        }
+        if !negated && ordered.starts_with('!') {
+            if ordered.len() == 1 {
+                // a set of a single `!` is the same as the character
+                self.out.push('!');
+                return Ok(());
+            }
+            if !rest.is_empty() {
+                return Err(self.invalid("character set can't start with `!`"));
+            }
+            // only `:`, `[` and `-` may follow, and they may go first
+            ordered.remove(0);
+            ordered.insert(1, '!');
+        }
+        self.out.push('[');
+        if negated {
+            self.out.push('!');
+        }
+        self.out.push_str(&ordered);
+        self.out.push(']');
+        Ok(())
+    }
+}
+
+/// Returns true if `name` can be used as a name of a capture group
+pub(crate) fn is_group_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use {Pattern, PatternOptions};
+    use super::{Token, ClassItem, CharClass, ExtGlobKind};
+
+    fn roundtrip(pattern: &Pattern) {
+        let canonical = pattern.to_string();
+        let rebuilt = Pattern::from_tokens(&pattern.tokens()).unwrap();
+        assert_eq!(rebuilt.tokens(), pattern.tokens(), "{}", canonical);
+        assert_eq!(rebuilt.to_string(), canonical);
+    }
+
+    #[test]
+    fn canonical() {
+        for &(pattern, canonical) in &[
+            ("a/**/**/b", "a/**/b"),
+            ("(**)/(*).rs", "(**)/(*).rs"),
+            ("a/(?<x>**)/b", "a/(?<x>**)/b"),
+            ("x[*][]][!]a-c][[:alpha:]_-]", "x[*]][!]a-c][[:alpha:]_-]"),
+            ("{0..010}{a,[,]b}", "{000..010}{a,[,]b}"),
+            ("a{b}[{]", "a[{]b}[{]"),
+            ("a/**/", "a/**"),
+            ("![?]@(x)", "![?]@(x)"),
+        ] {
+            let pat = Pattern::new_with(pattern, &PatternOptions {
+                braces: true,
+                .. PatternOptions::new()
+            }).unwrap();
+            assert_eq!(pat.to_string(), canonical);
+            assert_eq!(pat.as_str(), pattern);
+            roundtrip(&pat);
+        }
+    }
+
+    #[test]
+    fn extglob() {
+        let options = PatternOptions {
+            extglob: true,
+            braces: true,
+        };
+        for &(pattern, canonical) in &[
+            ("*(a|b[|])+(c)", "*(a|b[|])+(c)"),
+            ("?{,}(a)*{,}(b)!{,}(c)", "?{,}(a)*{,}(b)!{,}(c)"),
+            ("@({a,b}|c)", "@({a,b}|c)"),
+        ] {
+            let pat = Pattern::new_with(pattern, &options).unwrap();
+            assert_eq!(pat.to_string(), canonical);
+            roundtrip(&pat);
+        }
+        let pat = Pattern::new_with("!(a)", &options).unwrap();
+        assert_eq!(pat.tokens(), vec![
+            Token::ExtGlob {
+                kind: ExtGlobKind::Not,
+                index: 1,
+                alternatives: vec![vec![Token::Literal("a".into())]],
+            },
+        ]);
+    }
+
+    #[test]
+    fn classes() {
+        let class = |negated, items: &[ClassItem]| {
+            Pattern::from_tokens(&[Token::Class {
+                negated,
+                items: items.to_vec(),
+            }]).map(|p| p.to_string())
+        };
+        use super::ClassItem::*;
+        assert_eq!(class(false, &[Char('!')]).unwrap(), "!");
+        assert_eq!(class(false, &[Char('!'), Char('-')]).unwrap(), "[-!]");
+        assert_eq!(class(true, &[Char('!'), Char('-')]).unwrap(), "[!!-]");
+        assert_eq!(class(false, &[Char('['), Char(':'), Char('!')]).unwrap(),
+                   "[:![]");
+        assert_eq!(class(false, &[Char('-'), Range('a', 'z'), Char(']')])
+                   .unwrap(), "[]a-z-]");
+        assert_eq!(class(false, &[Posix(CharClass::Digit), Char('[')])
+                   .unwrap(), "[[:digit:][]");
+        assert!(class(false, &[]).is_err());
+        assert!(class(false, &[Range('!', 'z')]).is_err());
+    }
+
+    #[test]
+    fn build() {
+        let err = Pattern::from_tokens(&[
+            Token::Literal("a(".into()),
+            Token::RecursiveSequence,
+            Token::Group {
+                index: 7,
+                name: None,
+                tokens: vec![
+                    Token::Alternatives(vec![
+                        vec![Token::Literal("x,y".into())],
+                        vec![Token::NumRange { first: 1, last: 2, width: 0 }],
+                    ]),
+                ],
+            },
+        ]).unwrap_err();
+        // `**` is not a whole path component in `a[(]**/...`
+        assert_eq!(err.pos, 3);
+
+        let pat = Pattern::from_tokens(&[
+            Token::Literal("a(/".into()),
+            Token::RecursiveSequence,
+            Token::Group {
+                index: 7,
+                name: Some("g".into()),
+                tokens: vec![
+                    Token::Alternatives(vec![
+                        vec![Token::Literal("x,y".into())],
+                        vec![Token::NumRange { first: 1, last: 2, width: 0 }],
+                    ]),
+                ],
+            },
+        ]).unwrap();
+        assert_eq!(pat.to_string(), "a[(]/**/(?<g>{x[,]y,{1..2}})");
+        assert!(pat.matches("a(/b/x,y"));
+        assert!(pat.matches("a(/2"));
+        assert_eq!(pat.group_index("g"), Some(1));
+        assert_eq!(pat.captures("a(/b/2").unwrap().group(1).unwrap(), "2");
+
+        assert!(Pattern::from_tokens(&[Token::Group {
+            index: 1,
+            name: Some("not valid".into()),
+            tokens: vec![],
+        }]).is_err());
+        assert!(Pattern::from_tokens(&[Token::Alternatives(vec![])]).is_err());
+    }
+}
@@ -0,0 +1,608 @@
+//! Support for `Pattern::overlaps` and `Pattern::is_subset_of`
+//!
+//! Tokens are converted into an NFA whose transitions are labeled with
+//! character predicates. Two automata are then explored in lockstep,
+//! determinizing them on the fly. Characters only matter through the label
+//! predicates (and whether they are separators or dots), so the automata
+//! are explored over one representative of every class of characters that
+//! give the same results for all of them, which makes the search exact.
+//! Strings found this way are still double-checked with the regular matcher
+//! by the caller.
+
+use std::char;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use {PatternToken, CharSpecifier, MatchOptions, Separator};
+use {chars_eq, fold_case, in_char_specifiers};
+use ast::ExtGlobKind;
+
+#[derive(Clone, Debug)]
+enum LabelKind {
+    Char(char),
+    Any,
+    Within(Vec<CharSpecifier>),
+    Except(Vec<CharSpecifier>),
+    Digit(char),
+    Separator,
+}
+
+#[derive(Clone, Debug)]
+struct Label {
+    kind: LabelKind,
+    // wildcards don't match a leading dot with `require_literal_leading_dot`
+    wildcard: bool,
+    no_separator: bool,
+}
+
+#[derive(Default, Debug)]
+struct State {
+    eps: Vec<usize>,
+    // only taken when the string ends, as `**` that runs out of characters
+    // still lets the rest of the pattern match the empty string
+    end_eps: Vec<usize>,
+    edges: Vec<(Label, usize)>,
+    // `!(...)` with the start and accepting states of its alternatives, and
+    // the state to continue from
+    not: Option<(usize, usize, usize)>,
+}
+
+/// An NFA for the tokens of a single pattern
+pub(crate) struct Nfa<'a> {
+    states: Vec<State>,
+    start: usize,
+    accept: usize,
+    options: &'a MatchOptions,
+}
+
+// An element of the set of active states
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+enum Elem {
+    Simple(usize),
+    // inside of `!(...)` started at the state: the active states of the
+    // alternatives, whether the piece is still empty and whether it started
+    // with a dot that can't be matched
+    Not(usize, BTreeSet<Elem>, bool, bool),
+}
+
+type Set = BTreeSet<Elem>;
+
+impl<'a> Nfa<'a> {
+    pub(crate) fn new(tokens: &[PatternToken], options: &'a MatchOptions)
+        -> Nfa<'a>
+    {
+        let mut nfa = Nfa {
+            states: vec![State::default()],
+            start: 0,
+            accept: 0,
+            options,
+        };
+        nfa.accept = nfa.build(tokens, 0, false);
+        nfa
+    }
+
+    fn add_state(&mut self) -> usize {
+        self.states.push(State::default());
+        self.states.len() - 1
+    }
+
+    fn add_edge(&mut self, from: usize, kind: LabelKind, wildcard: bool,
+                no_separator: bool)
+        -> usize
+    {
+        let to = self.add_state();
+        self.states[from].edges.push((Label { kind, wildcard, no_separator }, to));
+        to
+    }
+
+    // Adds states for the tokens starting from the state `start` and
+    // returns the final state
+    fn build(&mut self, tokens: &[PatternToken], start: usize,
+             in_extglob: bool)
+        -> usize
+    {
+        use PatternToken::*;
+
+        let no_separator = in_extglob || self.options.require_literal_separator;
+        let mut cur = start;
+        for token in tokens {
+            cur = match *token {
+                Char(c) => self.add_edge(cur, LabelKind::Char(c), false, false),
+                AnyChar => self.add_edge(cur, LabelKind::Any, true, no_separator),
+                AnyWithin(ref specs) => {
+                    self.add_edge(cur, LabelKind::Within(specs.clone()), true,
+                                  no_separator)
+                }
+                AnyExcept(ref specs) => {
+                    self.add_edge(cur, LabelKind::Except(specs.clone()), true,
+                                  no_separator)
+                }
+                AnySequence => {
+                    let next = self.add_state();
+                    self.states[cur].eps.push(next);
+                    let label = Label {
+                        kind: LabelKind::Any,
+                        wildcard: true,
+                        no_separator,
+                    };
+                    self.states[next].edges.push((label, next));
+                    next
+                }
+                AnyRecursiveSequence => {
+                    let inner = self.add_edge(cur, LabelKind::Any, true, false);
+                    let any = Label {
+                        kind: LabelKind::Any,
+                        wildcard: true,
+                        no_separator: false,
+                    };
+                    self.states[inner].edges.push((any, inner));
+                    let next = self.add_state();
+                    let separator = Label {
+                        kind: LabelKind::Separator,
+                        wildcard: true,
+                        no_separator: false,
+                    };
+                    self.states[cur].edges.push((separator.clone(), next));
+                    self.states[inner].edges.push((separator, next));
+                    self.states[cur].eps.push(next);
+                    self.states[inner].end_eps.push(next);
+                    next
+                }
+                StartCapture(..) | EndCapture(..) => cur,
+                NumRange(first, last, width) => {
+                    self.build_num_range(cur, first, last, width)
+                }
+                ExtGlob(ExtGlobKind::Not, ref alternatives, _) => {
+                    let sub_start = self.add_state();
+                    let sub_accept = self.add_state();
+                    for alt in alternatives {
+                        let end = self.build(alt, sub_start, true);
+                        self.states[end].eps.push(sub_accept);
+                    }
+                    // alternatives may start at the same state, so `!(...)`
+                    // needs a state of its own
+                    let entry = self.add_state();
+                    let next = self.add_state();
+                    self.states[cur].eps.push(entry);
+                    self.states[entry].not = Some((sub_start, sub_accept, next));
+                    next
+                }
+                ExtGlob(kind, ref alternatives, _) => {
+                    let entry = self.add_state();
+                    let exit = self.add_state();
+                    for alt in alternatives {
+                        let end = self.build(alt, entry, true);
+                        self.states[end].eps.push(exit);
+                    }
+                    let next = self.add_state();
+                    self.states[cur].eps.push(entry);
+                    self.states[exit].eps.push(next);
+                    match kind {
+                        ExtGlobKind::ZeroOrOne => {
+                            self.states[cur].eps.push(next);
+                        }
+                        ExtGlobKind::ZeroOrMore => {
+                            self.states[cur].eps.push(next);
+                            self.states[exit].eps.push(entry);
+                        }
+                        ExtGlobKind::OneOrMore => {
+                            self.states[exit].eps.push(entry);
+                        }
+                        _ => {}
+                    }
+                    next
+                }
+            };
+        }
+        cur
+    }
+
+    // Adds states for the numbers in the range, one chain per length
+    fn build_num_range(&mut self, start: usize, first: u64, last: u64,
+                       width: usize)
+        -> usize
+    {
+        let (low, high) = if first <= last { (first, last) } else { (last, first) };
+        let next = self.add_state();
+        let lengths = if width > 0 {
+            width..width + 1
+        } else {
+            low.to_string().len()..high.to_string().len() + 1
+        };
+        for len in lengths {
+            let (min, max) = if width > 0 {
+                (low, high)
+            } else {
+                let min = if len == 1 { 0 } else { 10u64.pow(len as u32 - 1) };
+                let max = 10u64.checked_pow(len as u32).map_or(u64::MAX, |n| n - 1);
+                (low.max(min), high.min(max))
+            };
+            if min > max {
+                continue;
+            }
+            let min = format!("{:01$}", min, len).into_bytes();
+            let max = format!("{:01$}", max, len).into_bytes();
+            // states by the position and whether the digits so far are
+            // equal to those of the bounds
+            let mut layer = HashMap::new();
+            layer.insert((true, true), start);
+            for i in 0..len {
+                let mut next_layer = HashMap::new();
+                for (&(at_min, at_max), &state) in &layer {
+                    let from = if at_min { min[i] } else { b'0' };
+                    let to = if at_max { max[i] } else { b'9' };
+                    for digit in from..to + 1 {
+                        let key = (at_min && digit == min[i], at_max && digit == max[i]);
+                        let target = if i + 1 == len {
+                            next
+                        } else if let Some(&target) = next_layer.get(&key) {
+                            target
+                        } else {
+                            let target = self.add_state();
+                            next_layer.insert(key, target);
+                            target
+                        };
+                        let label = Label {
+                            kind: LabelKind::Digit(digit as char),
+                            wildcard: false,
+                            no_separator: false,
+                        };
+                        self.states[state].edges.push((label, target));
+                    }
+                }
+                layer = next_layer;
+            }
+        }
+        next
+    }
+
+    fn label_matches(&self, label: &Label, c: char, follows_separator: bool)
+        -> bool
+    {
+        let is_sep = self.options.separator.is_separator(c);
+        if label.no_separator && is_sep {
+            return false;
+        }
+        if label.wildcard && follows_separator &&
+            self.options.require_literal_leading_dot && c == '.'
+        {
+            return false;
+        }
+        match label.kind {
+            LabelKind::Char(c2) => chars_eq(c, c2, self.options),
+            LabelKind::Any => true,
+            LabelKind::Within(ref specs) => {
+                in_char_specifiers(specs, c, self.options)
+            }
+            LabelKind::Except(ref specs) => {
+                !in_char_specifiers(specs, c, self.options)
+            }
+            LabelKind::Digit(d) => c == d,
+            LabelKind::Separator => is_sep,
+        }
+    }
+
+    fn start_set(&self) -> Set {
+        let mut set = Set::new();
+        self.close(Elem::Simple(self.start), &mut set, false);
+        set
+    }
+
+    // Adds the element and everything reachable by epsilon transitions
+    fn close(&self, elem: Elem, set: &mut Set, at_end: bool) {
+        if set.contains(&elem) {
+            return;
+        }
+        let state = match elem {
+            Elem::Simple(s) => s,
+            Elem::Not(s, ref sub, _, blocked) => {
+                let (_, sub_accept, next) = self.states[s].not.unwrap();
+                if !blocked && !sub.contains(&Elem::Simple(sub_accept)) {
+                    self.close(Elem::Simple(next), set, at_end);
+                }
+                set.insert(elem.clone());
+                return;
+            }
+        };
+        set.insert(elem);
+        for &next in &self.states[state].eps {
+            self.close(Elem::Simple(next), set, at_end);
+        }
+        if at_end {
+            for &next in &self.states[state].end_eps {
+                self.close(Elem::Simple(next), set, at_end);
+            }
+        }
+        if let Some((sub_start, _, _)) = self.states[state].not {
+            let mut sub = Set::new();
+            self.close(Elem::Simple(sub_start), &mut sub, false);
+            self.close(Elem::Not(state, sub, true, false), set, at_end);
+        }
+    }
+
+    fn step(&self, set: &Set, c: char, follows_separator: bool) -> Set {
+        let mut result = Set::new();
+        for elem in set {
+            match *elem {
+                Elem::Simple(s) => {
+                    for &(ref label, next) in &self.states[s].edges {
+                        if self.label_matches(label, c, follows_separator) {
+                            self.close(Elem::Simple(next), &mut result, false);
+                        }
+                    }
+                }
+                Elem::Not(s, ref sub, empty, blocked) => {
+                    // the piece never spans path components
+                    if self.options.separator.is_separator(c) {
+                        continue;
+                    }
+                    let blocked = blocked || empty && follows_separator &&
+                        self.options.require_literal_leading_dot && c == '.';
+                    let sub = self.step(sub, c, follows_separator);
+                    self.close(Elem::Not(s, sub, false, blocked), &mut result,
+                               false);
+                }
+            }
+        }
+        result
+    }
+
+    fn accepts(&self, set: &Set) -> bool {
+        let mut closed = Set::new();
+        for elem in set {
+            self.close(elem.clone(), &mut closed, true);
+        }
+        closed.contains(&Elem::Simple(self.accept))
+    }
+
+    // Collects the characters that are treated specially by the automaton,
+    // along with the ones following them and the range bounds, so that
+    // every run of characters that are compared only by equality and ranges
+    // starts with one of them
+    fn mentioned_chars(&self, chars: &mut Vec<char>) {
+        let mut add = |c: char| {
+            let variants = Some(c).into_iter()
+                .chain(Some(fold_case(c)))
+                .chain(c.to_lowercase())
+                .chain(c.to_uppercase());
+            for v in variants {
+                chars.push(v);
+                chars.extend(char::from_u32(v as u32 + 1));
+            }
+        };
+        for state in &self.states {
+            for (label, _) in &state.edges {
+                match label.kind {
+                    LabelKind::Char(c) | LabelKind::Digit(c) => add(c),
+                    LabelKind::Within(ref specs) | LabelKind::Except(ref specs) => {
+                        for spec in specs {
+                            match *spec {
+                                CharSpecifier::SingleChar(c) => add(c),
+                                CharSpecifier::CharRange(a, b) => {
+                                    add(a);
+                                    add(b);
+                                }
+                                CharSpecifier::Class(_) => {}
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Collects the distinct character predicates of the labels, characters
+    // for which all of them give the same result are interchangeable
+    fn predicates<'b>(&'b self, preds: &mut Vec<Predicate<'b>>) {
+        for state in &self.states {
+            for (label, _) in &state.edges {
+                let pred = match label.kind {
+                    LabelKind::Char(c) => Predicate::Char(c),
+                    LabelKind::Digit(c) => Predicate::Digit(c),
+                    LabelKind::Within(ref specs) | LabelKind::Except(ref specs)
+                    => Predicate::Within(specs),
+                    LabelKind::Any | LabelKind::Separator => continue,
+                };
+                if !preds.contains(&pred) {
+                    preds.push(pred);
+                }
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum Predicate<'a> {
+    Char(char),
+    Digit(char),
+    Within(&'a [CharSpecifier]),
+}
+
+/// What to look for in `search`
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub(crate) enum Goal {
+    /// A string matched by both automata
+    Both,
+    /// A string matched by the first automaton but not the second one
+    FirstOnly,
+}
+
+/// Explores the product of the automata breadth-first, passing every
+/// string that meets the goal to `check` until it returns true
+pub(crate) fn search<F>(a: &Nfa, b: &Nfa, goal: Goal, mut check: F)
+    -> Option<String>
+    where F: FnMut(&str) -> bool
+{
+    let alphabet = alphabet(a, b);
+    let start = (a.start_set(), b.start_set(), true);
+    // every visited configuration with its parent and the character that
+    // leads to it
+    let mut visited = HashMap::new();
+    let mut parents = vec![(usize::MAX, '\0')];
+    visited.insert(start.clone(), 0);
+    let mut queue = VecDeque::new();
+    queue.push_back((start, 0));
+    while let Some(((sa, sb, follows_separator), idx)) = queue.pop_front() {
+        let found = a.accepts(&sa) && match goal {
+            Goal::Both => b.accepts(&sb),
+            Goal::FirstOnly => !b.accepts(&sb),
+        };
+        if found {
+            let mut chars = Vec::new();
+            let mut cur = idx;
+            while cur != 0 {
+                chars.push(parents[cur].1);
+                cur = parents[cur].0;
+            }
+            let witness = chars.iter().rev().collect::<String>();
+            if check(&witness) {
+                return Some(witness);
+            }
+        }
+        for &c in &alphabet {
+            let na = a.step(&sa, c, follows_separator);
+            if na.is_empty() {
+                continue;
+            }
+            let nb = b.step(&sb, c, follows_separator);
+            if nb.is_empty() && goal == Goal::Both {
+                continue;
+            }
+            let config = (na, nb, a.options.separator.is_separator(c));
+            if visited.contains_key(&config) {
+                continue;
+            }
+            parents.push((idx, c));
+            visited.insert(config.clone(), parents.len() - 1);
+            queue.push_back((config, parents.len() - 1));
+        }
+    }
+    None
+}
+
+// One representative of every class of characters that the automata can't
+// tell apart, which makes the search exhaustive. The characters that make
+// nicer examples go first.
+fn alphabet(a: &Nfa, b: &Nfa) -> Vec<char> {
+    let options = a.options;
+    let mut preds = Vec::new();
+    a.predicates(&mut preds);
+    b.predicates(&mut preds);
+    // besides the labels, separators and dots are treated specially
+    let signature = |c: char, sig: &mut Vec<bool>| {
+        sig.clear();
+        sig.push(options.separator.is_separator(c));
+        sig.push(c == '.');
+        sig.extend(preds.iter().map(|pred| match *pred {
+            Predicate::Char(c2) => chars_eq(c, c2, options),
+            Predicate::Digit(d) => c == d,
+            Predicate::Within(specs) => in_char_specifiers(specs, c, options),
+        }));
+    };
+    let mut mentioned = Vec::new();
+    a.mentioned_chars(&mut mentioned);
+    b.mentioned_chars(&mut mentioned);
+    if let Separator::Char(sep) = options.separator {
+        mentioned.push(sep);
+        mentioned.extend(char::from_u32(sep as u32 + 1));
+    }
+    // Otherwise non-ASCII characters are only compared for equality and
+    // against ranges, so the mentioned ones and the first one after ASCII
+    // start all of the runs of characters that are the same for automata.
+    // Only classes with `unicode_classes` and case folding of characters
+    // that have case need every character to be tried.
+    let has_case = |c: char| {
+        !c.to_lowercase().eq(Some(c)) || !c.to_uppercase().eq(Some(c))
+    };
+    let classes = options.unicode_classes && preds.iter().any(|pred| {
+        match *pred {
+            Predicate::Within(specs) => specs.iter().any(|spec| {
+                matches!(*spec, CharSpecifier::Class(_))
+            }),
+            _ => false,
+        }
+    });
+    let last = if classes || !options.case_sensitive {
+        char::MAX as u32
+    } else {
+        0x80
+    };
+    let everything = (0..=last).filter_map(char::from_u32)
+        .filter(|&c| c <= '\u{80}' || classes || has_case(c));
+    let mut seen = HashSet::new();
+    let mut sig = Vec::new();
+    let mut prev = Vec::new();
+    let mut result = Vec::new();
+    for c in mentioned.into_iter()
+        .chain("abcxyzABCXYZ019._-/".chars())
+        .chain(everything)
+    {
+        signature(c, &mut sig);
+        // consecutive characters are mostly in the same class
+        if sig == prev {
+            continue;
+        }
+        if !seen.contains(&sig) {
+            seen.insert(sig.clone());
+            result.push(c);
+        }
+        ::std::mem::swap(&mut sig, &mut prev);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use {Pattern, PatternOptions, MatchOptions};
+    use super::{Nfa, Goal, search};
+
+    fn braces(pattern: &str) -> Pattern {
+        Pattern::new_with(pattern, &PatternOptions {
+            braces: true,
+            .. PatternOptions::new()
+        }).unwrap()
+    }
+
+    fn nfa_matches(pattern: &str, s: &str) -> bool {
+        let options = MatchOptions::new();
+        let pat = braces(pattern);
+        let nfa = Nfa::new(&pat.tokens, &options);
+        let mut set = nfa.start_set();
+        let mut follows_separator = true;
+        for c in s.chars() {
+            set = nfa.step(&set, c, follows_separator);
+            follows_separator = c == '/';
+        }
+        nfa.accepts(&set)
+    }
+
+    #[test]
+    fn agrees_with_matcher() {
+        let cases = &[
+            ("a*b", &["ab", "axxb", "a/b", "ba"][..]),
+            ("**/b", &["b", "x/b", "x/y/b", "xb"][..]),
+            ("a/**", &["a/", "a/x", "a/x/y", "a"][..]),
+            ("{1..12}x", &["1x", "12x", "13x", "01x", "0x"][..]),
+            ("{08..10}", &["08", "09", "10", "8", "11"][..]),
+            ("[a-c][!x]", &["ay", "cx", "dy"][..]),
+            ("{a,b*}c", &["ac", "bc", "bxxc", "c"][..]),
+        ];
+        for &(pattern, strings) in cases {
+            let pat = braces(pattern);
+            for s in strings {
+                assert_eq!(nfa_matches(pattern, s), pat.matches(s),
+                           "{} {}", pattern, s);
+            }
+        }
+    }
+
+    #[test]
+    fn search_shortest() {
+        let options = MatchOptions::new();
+        let a = Pattern::new("*.rs").unwrap();
+        let b = Pattern::new("lib*").unwrap();
+        let (na, nb) = (Nfa::new(&a.tokens, &options), Nfa::new(&b.tokens, &options));
+        assert_eq!(search(&na, &nb, Goal::Both, |_| true).unwrap(), "lib.rs");
+        assert_eq!(search(&na, &nb, Goal::FirstOnly, |_| true).unwrap(), ".rs");
+    }
+}
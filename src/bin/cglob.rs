@@ -0,0 +1,409 @@
+//! Print the paths matching a glob pattern along with the capture groups
+//!
+//! Exits with `0` if anything matched, `1` if nothing did and `2` on
+//! errors. See `cglob --help` for the options.
+
+extern crate capturing_glob;
+
+use std::env;
+use std::ffi::OsStr;
+use std::io::{self, BufWriter, Write};
+use std::process::exit;
+
+use capturing_glob::{glob_with_options, Entry, ErrorPolicy, MatchOptions};
+use capturing_glob::{Pattern, PatternOptions, Rename, RenamePlan, Separator};
+use capturing_glob::Template;
+
+
+const USAGE: &str = "\
+Usage: cglob [OPTIONS] PATTERN
+
+Print the paths matching PATTERN along with the capture groups, e.g.
+`cglob --tsv 'src/(**)/(*).rs'`.
+
+Output:
+    --tsv                   Print the path and the groups separated by tabs
+    --json                  Print a JSON object per line
+    -0, --null              Terminate the records by NUL instead of newline
+    --to TEMPLATE           Print the path made from the groups, e.g.
+                            `out/$1/${2:lower}.o` (see `Template` for the
+                            syntax); a TEMPLATE without `$` is a pattern,
+                            groups of which are filled in order (see
+                            `Pattern::substitute`)
+
+Renaming:
+    --rename                Move the matched paths to the `--to` paths,
+                            printing the moves as `FROM<TAB>TO`; the moves
+                            are checked for collisions and reverted if one
+                            of them fails
+    -n, --dry-run           Only print the moves
+
+Matching:
+    -i, --case-insensitive  Ignore case
+    --require-literal-leading-dot
+                            Don't match hidden files by wildcards
+    --normalization-insensitive
+                            Compare Unicode-normalized names
+    --unicode-classes       Match `[[:alpha:]]` and alike by Unicode
+    --separator SEP         `platform` (default), `slash` or a character
+    --extglob               Enable `@(..)`, `!(..)` and alike
+    --braces                Enable `{a,b}` and `{1..20}`
+
+Walk:
+    --errors POLICY         What to do with unreadable directories: `yield`
+                            (print and exit with 2, default), `skip` or
+                            `abort`
+
+    -h, --help              Print this help
+    -V, --version           Print the version
+
+Exits with 0 if anything matched, 1 if nothing did, and 2 on errors.
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Paths,
+    Tsv,
+    Json,
+}
+
+#[derive(Debug)]
+struct Options {
+    pattern: String,
+    to: Option<String>,
+    rename: bool,
+    dry_run: bool,
+    format: Format,
+    terminator: u8,
+    syntax: PatternOptions,
+    matching: MatchOptions,
+    errors: ErrorPolicy,
+}
+
+// The `--to` argument
+#[derive(Debug)]
+enum Target {
+    Pattern(Pattern),
+    Template(Template),
+}
+
+fn parse_args<I: Iterator<Item=String>>(mut args: I)
+    -> Result<Options, String>
+{
+    let mut pattern = None;
+    let mut options = Options {
+        pattern: String::new(),
+        to: None,
+        rename: false,
+        dry_run: false,
+        format: Format::Paths,
+        terminator: b'\n',
+        syntax: PatternOptions::new(),
+        // `glob_with()` always matches separators literally, and so
+        // should the capture groups
+        matching: MatchOptions {
+            require_literal_separator: true,
+            .. MatchOptions::new()
+        },
+        errors: ErrorPolicy::Yield,
+    };
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = || -> Result<String, String> {
+            inline.clone().or_else(|| args.next())
+                .ok_or_else(|| format!("option {} requires a value", flag))
+        };
+        match &flag[..] {
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                exit(0);
+            }
+            "-V" | "--version" => {
+                println!("cglob {}", env!("CARGO_PKG_VERSION"));
+                exit(0);
+            }
+            "--tsv" => options.format = Format::Tsv,
+            "--json" => options.format = Format::Json,
+            "-0" | "--null" => options.terminator = b'\0',
+            "--to" => options.to = Some(value()?),
+            "--rename" => options.rename = true,
+            "-n" | "--dry-run" => options.dry_run = true,
+            "-i" | "--case-insensitive" => {
+                options.matching.case_sensitive = false;
+            }
+            "--require-literal-leading-dot" => {
+                options.matching.require_literal_leading_dot = true;
+            }
+            "--normalization-insensitive" => {
+                options.matching.normalization_insensitive = true;
+            }
+            "--unicode-classes" => options.matching.unicode_classes = true,
+            "--separator" => {
+                let value = value()?;
+                options.matching.separator = match &value[..] {
+                    "platform" => Separator::Platform,
+                    "slash" => Separator::Slash,
+                    _ => {
+                        let mut chars = value.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => Separator::Char(c),
+                            _ => return Err(format!(
+                                "invalid separator {:?}", value)),
+                        }
+                    }
+                };
+            }
+            "--extglob" => options.syntax.extglob = true,
+            "--braces" => options.syntax.braces = true,
+            "--errors" => {
+                let value = value()?;
+                options.errors = match &value[..] {
+                    "yield" => ErrorPolicy::Yield,
+                    "skip" => ErrorPolicy::Skip,
+                    "abort" => ErrorPolicy::Abort,
+                    _ => return Err(format!(
+                        "invalid error policy {:?}", value)),
+                };
+            }
+            "--" => {
+                pattern = args.next();
+                break;
+            }
+            _ if arg.starts_with('-') && arg.len() > 1 => {
+                return Err(format!("unknown option {}", arg));
+            }
+            _ if pattern.is_none() => pattern = Some(arg),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    if let Some(extra) = args.next() {
+        return Err(format!("unexpected argument {}", extra));
+    }
+    options.pattern = pattern.ok_or("no pattern given")?;
+    if options.rename && options.to.is_none() {
+        return Err("--rename requires --to".into());
+    }
+    Ok(options)
+}
+
+fn parse_target(target: &str, source: &Pattern, syntax: &PatternOptions)
+    -> Result<Target, String>
+{
+    if target.contains('$') {
+        Template::new(target, source).map(Target::Template)
+            .map_err(|e| format!("invalid target {:?}: {}", target, e))
+    } else {
+        Pattern::new_with(target, syntax).map(Target::Pattern)
+            .map_err(|e| format!("invalid target {:?}: {}", target, e))
+    }
+}
+
+fn substitute(target: &Target, entry: &Entry) -> Result<String, String> {
+    match *target {
+        Target::Pattern(ref pattern) => {
+            let values = (1..pattern.group_count() + 1)
+                .map(|n| entry.group(n).and_then(|g| g.to_str()).unwrap_or(""))
+                .collect::<Vec<_>>();
+            pattern.substitute(&values).map_err(|e| {
+                format!("can't substitute {:?}: {}", entry.path(), e)
+            })
+        }
+        Target::Template(ref template) => Ok(template.render(entry)),
+    }
+}
+
+fn json_string(out: &mut dyn Write, value: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => out.write_all(b"\\\"")?,
+            '\\' => out.write_all(b"\\\\")?,
+            '\n' => out.write_all(b"\\n")?,
+            '\r' => out.write_all(b"\\r")?,
+            '\t' => out.write_all(b"\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    out.write_all(b"\"")
+}
+
+#[cfg(unix)]
+fn write_os(out: &mut dyn Write, value: &OsStr) -> io::Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+    out.write_all(value.as_bytes())
+}
+
+#[cfg(not(unix))]
+fn write_os(out: &mut dyn Write, value: &OsStr) -> io::Result<()> {
+    write!(out, "{}", value.to_string_lossy())
+}
+
+fn write_entry(out: &mut dyn Write, options: &Options, entry: &Entry,
+               target: Option<&str>, group_count: usize)
+    -> io::Result<()>
+{
+    let group = |n| entry.group(n).unwrap_or_else(|| OsStr::new(""));
+    match options.format {
+        Format::Paths => match target {
+            Some(target) => out.write_all(target.as_bytes())?,
+            None => write_os(out, entry.path().as_os_str())?,
+        },
+        Format::Tsv => {
+            write_os(out, entry.path().as_os_str())?;
+            for n in 1..group_count + 1 {
+                out.write_all(b"\t")?;
+                write_os(out, group(n))?;
+            }
+            if let Some(target) = target {
+                out.write_all(b"\t")?;
+                out.write_all(target.as_bytes())?;
+            }
+        }
+        Format::Json => {
+            out.write_all(b"{\"path\":")?;
+            json_string(out, &entry.path().to_string_lossy())?;
+            out.write_all(b",\"groups\":[")?;
+            for n in 1..group_count + 1 {
+                if n > 1 {
+                    out.write_all(b",")?;
+                }
+                json_string(out, &group(n).to_string_lossy())?;
+            }
+            out.write_all(b"]")?;
+            let names = (1..group_count + 1)
+                .filter_map(|n| entry.group_name(n).map(|name| (n, name)))
+                .collect::<Vec<_>>();
+            if !names.is_empty() {
+                out.write_all(b",\"names\":{")?;
+                for (i, &(n, name)) in names.iter().enumerate() {
+                    if i > 0 {
+                        out.write_all(b",")?;
+                    }
+                    json_string(out, name)?;
+                    out.write_all(b":")?;
+                    json_string(out, &group(n).to_string_lossy())?;
+                }
+                out.write_all(b"}")?;
+            }
+            if let Some(target) = target {
+                out.write_all(b",\"to\":")?;
+                json_string(out, target)?;
+            }
+            out.write_all(b"}")?;
+        }
+    }
+    out.write_all(&[options.terminator])
+}
+
+// Returns the exit code
+fn run(options: &Options) -> Result<i32, String> {
+    let source = Pattern::new_with(&options.pattern, &options.syntax)
+        .map_err(|e| format!("invalid pattern {:?}: {}", options.pattern, e))?;
+    let target = match options.to {
+        Some(ref template) => {
+            Some(parse_target(template, &source, &options.syntax)?)
+        }
+        None => None,
+    };
+    let entries = glob_with_options(&options.pattern, &options.syntax,
+                                    &options.matching)
+        .map_err(|e| format!("invalid pattern {:?}: {}", options.pattern, e))?
+        .error_policy(options.errors);
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut matched = false;
+    let mut failed = false;
+    let mut moves = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("cglob: {}", e);
+                failed = true;
+                continue;
+            }
+        };
+        let target = match target {
+            Some(ref target) => match substitute(target, &entry) {
+                Ok(path) => Some(path),
+                Err(e) => {
+                    eprintln!("cglob: {}", e);
+                    failed = true;
+                    continue;
+                }
+            },
+            None => None,
+        };
+        matched = true;
+        if options.rename {
+            moves.push(Rename {
+                from: entry.path().to_path_buf(),
+                to: target.unwrap_or_default().into(),
+            });
+            continue;
+        }
+        let written = write_entry(&mut out, options, &entry,
+                                  target.as_deref(), source.group_count());
+        if let Err(e) = written {
+            return output_error(e);
+        }
+    }
+    if options.rename && !failed {
+        let plan = RenamePlan::new(moves).map_err(|e| e.to_string())?;
+        if !options.dry_run {
+            plan.apply().map_err(|e| e.to_string())?;
+        }
+        for step in plan.steps() {
+            let written = write_os(&mut out, step.from.as_os_str())
+                .and_then(|()| out.write_all(b"\t"))
+                .and_then(|()| write_os(&mut out, step.to.as_os_str()))
+                .and_then(|()| out.write_all(&[options.terminator]));
+            if let Err(e) = written {
+                return output_error(e);
+            }
+        }
+    }
+    if let Err(e) = out.flush() {
+        return output_error(e);
+    }
+    Ok(if failed { 2 } else if matched { 0 } else { 1 })
+}
+
+fn output_error(e: io::Error) -> Result<i32, String> {
+    if e.kind() == io::ErrorKind::BrokenPipe {
+        // the reader has seen enough, e.g. `cglob .. | head`
+        Ok(0)
+    } else {
+        Err(format!("can't write output: {}", e))
+    }
+}
+
+fn main() {
+    let args = env::args_os().skip(1)
+        .map(|arg| arg.into_string().map_err(|arg| {
+            format!("argument {:?} is not valid UTF-8", arg)
+        }))
+        .collect::<Result<Vec<_>, _>>();
+    let options = match args.and_then(|args| parse_args(args.into_iter())) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("cglob: {}\nTry `cglob --help` for more information.",
+                      e);
+            exit(2);
+        }
+    };
+    match run(&options) {
+        Ok(code) => exit(code),
+        Err(e) => {
+            eprintln!("cglob: {}", e);
+            exit(2);
+        }
+    }
+}
@@ -1,12 +1,15 @@
 use std::str::from_utf8;
 use std::path::{Path, PathBuf};
 use std::ffi::OsStr;
+use std::sync::Arc;
 
 /// Entry that contains file path as well as all capture groups if any
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Entry {
     path: PathBuf,
     groups: Vec<(usize, usize)>,
+    // names of the groups, if the pattern has any
+    names: Option<Arc<[Option<String>]>>,
 }
 
 impl Entry {
@@ -14,6 +17,7 @@ impl Entry {
         Entry {
             path,
             groups: Vec::new(),
+            names: None,
         }
     }
     pub(crate) fn with_captures<P>(path: P, capt: Vec<(usize, usize)>)
@@ -23,12 +27,44 @@ impl Entry {
         Entry {
             path: path.into(),
             groups: capt,
+            names: None,
         }
     }
+    pub(crate) fn with_names(mut self, names: &Arc<[Option<String>]>)
+        -> Entry
+    {
+        if names.iter().any(|n| n.is_some()) {
+            self.names = Some(names.clone());
+        }
+        self
+    }
     /// Get path represented by this entry
     pub fn path(&self) -> &Path {
         &self.path
     }
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn spans(&self) -> &[(usize, usize)] {
+        &self.groups
+    }
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn names(&self) -> Option<&Arc<[Option<String>]>> {
+        self.names.as_ref()
+    }
+    /// Get the name of capture group number `n`, if the group is named
+    /// like `(?<name>*)`
+    pub fn group_name(&self, n: usize) -> Option<&str> {
+        self.names.as_ref()
+            .and_then(|names| names.get(n.checked_sub(1)?))
+            .and_then(|name| name.as_ref())
+            .map(|name| &name[..])
+    }
+    /// Get the capture group named `name`
+    pub fn named_group(&self, name: &str) -> Option<&OsStr> {
+        let names = self.names.as_ref()?;
+        let idx = names.iter()
+            .position(|n| n.as_ref().is_some_and(|n| n == name))?;
+        self.group(idx + 1)
+    }
     /// Get capture group number `n`
     ///
     /// The `n` is 1-based as in regexes (group 0 is the whole path)
@@ -66,9 +102,9 @@ impl Entry {
     }
 }
 
-impl Into<PathBuf> for Entry {
-    fn into(self) -> PathBuf {
-        self.path
+impl From<Entry> for PathBuf {
+    fn from(entry: Entry) -> PathBuf {
+        entry.path
     }
 }
 
@@ -77,3 +113,71 @@ impl AsRef<Path> for Entry {
         self.path.as_ref()
     }
 }
+
+/// Reusable buffer for spans of capture groups filled by
+/// `Pattern::captures_into()`
+///
+/// Spans are byte offsets into the matched string, which is not stored in
+/// the buffer, so it must be passed to `group()` again.
+#[derive(Debug, Clone, Default)]
+pub struct CaptureSpans {
+    len: usize,
+    groups: Vec<(usize, usize)>,
+}
+
+impl CaptureSpans {
+    /// Create an empty buffer
+    pub fn new() -> CaptureSpans {
+        CaptureSpans::default()
+    }
+    pub(crate) fn clear(&mut self, len: usize) {
+        self.len = len;
+        self.groups.clear();
+    }
+    pub(crate) fn buffer(&mut self) -> &mut Vec<(usize, usize)> {
+        &mut self.groups
+    }
+    /// Get the byte range of capture group number `n`
+    ///
+    /// The `n` is 1-based as in regexes (group 0 is the whole string)
+    pub fn span(&self, n: usize) -> Option<(usize, usize)> {
+        if n == 0 {
+            Some((0, self.len))
+        } else {
+            self.groups.get(n-1).cloned()
+        }
+    }
+    /// Get capture group number `n` of the matched string `text`
+    ///
+    /// The `n` is 1-based as in regexes (group 0 is the whole string)
+    ///
+    /// # Panics
+    ///
+    /// May panic if `text` is not the string that was matched last.
+    pub fn group<'a>(&self, text: &'a str, n: usize) -> Option<&'a str> {
+        self.span(n).map(|(a, b)| &text[a..b])
+    }
+}
+
+/// Capture groups of a string matched by `Pattern::captures_key()`
+#[derive(Debug, Clone)]
+pub struct KeyCaptures<'a> {
+    key: &'a str,
+    spans: CaptureSpans,
+}
+
+impl<'a> KeyCaptures<'a> {
+    pub(crate) fn new(key: &'a str, spans: CaptureSpans) -> KeyCaptures<'a> {
+        KeyCaptures { key, spans }
+    }
+    /// Get the matched string
+    pub fn key(&self) -> &'a str {
+        self.key
+    }
+    /// Get capture group number `n`
+    ///
+    /// The `n` is 1-based as in regexes (group 0 is the whole key)
+    pub fn group(&self, n: usize) -> Option<&'a str> {
+        self.spans.group(self.key, n)
+    }
+}
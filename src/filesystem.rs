@@ -0,0 +1,196 @@
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::ops::Bound;
+use std::path::{Component, Path, PathBuf};
+
+
+/// Access to the directory tree that is globbed, see `glob_in()`
+///
+/// Paths passed to the methods are built from the glob pattern: they are
+/// either relative (and start with a component of the pattern, the current
+/// directory is passed as `.`) or absolute, and may contain `..` components.
+pub trait FileSystem {
+    /// Return the names of the entries of the directory, in any order
+    ///
+    /// An error for the whole directory means it couldn't be listed, errors
+    /// of the individual entries are reported by the glob and the rest of
+    /// the entries are still used.
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>>;
+    /// Return true if the path exists and is a directory
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Return true if the path exists
+    fn exists(&self, path: &Path) -> bool;
+}
+
+impl<F: FileSystem + ?Sized> FileSystem for &F {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        (**self).read_dir(path)
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        (**self).is_dir(path)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+}
+
+/// The real file system, accessed with `std::fs`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFs;
+
+impl FileSystem for StdFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        Ok(fs::read_dir(path)?
+            .map(|e| e.map(|e| e.file_name()))
+            .collect())
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        fs::metadata(path).is_ok()
+    }
+}
+
+/// An in-memory directory tree, mostly useful for tests
+///
+/// # Examples
+///
+/// ```rust
+/// use std::path::Path;
+/// use capturing_glob::{glob_in, MemoryFs, MatchOptions};
+///
+/// let mut fs = MemoryFs::new();
+/// fs.add_file("src/lib.rs");
+/// fs.add_file("src/bin/tool.rs");
+/// fs.add_dir("target");
+///
+/// let paths = glob_in(&fs, "src/**/*.rs", &MatchOptions::new()).unwrap()
+///     .map(|entry| entry.unwrap().path().to_path_buf())
+///     .collect::<Vec<_>>();
+/// assert_eq!(paths, [Path::new("src/bin/tool.rs"), Path::new("src/lib.rs")]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MemoryFs {
+    dirs: BTreeSet<PathBuf>,
+    files: BTreeSet<PathBuf>,
+}
+
+impl MemoryFs {
+    /// Create an empty tree, containing only the current directory
+    pub fn new() -> MemoryFs {
+        let mut dirs = BTreeSet::new();
+        dirs.insert(PathBuf::new());
+        MemoryFs {
+            dirs,
+            files: BTreeSet::new(),
+        }
+    }
+    /// Add a file along with all its parent directories
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> &mut MemoryFs {
+        let path = normalize(path.as_ref());
+        if let Some(parent) = path.parent() {
+            self.add_dir(parent);
+        }
+        self.files.insert(path);
+        self
+    }
+    /// Add a directory along with all its parents
+    pub fn add_dir<P: AsRef<Path>>(&mut self, path: P) -> &mut MemoryFs {
+        let path = normalize(path.as_ref());
+        for dir in path.ancestors() {
+            self.dirs.insert(dir.to_path_buf());
+        }
+        self
+    }
+}
+
+impl Default for MemoryFs {
+    fn default() -> MemoryFs {
+        MemoryFs::new()
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        let path = normalize(path);
+        if !self.dirs.contains(&path) {
+            let kind = if self.files.contains(&path) {
+                io::ErrorKind::Other
+            } else {
+                io::ErrorKind::NotFound
+            };
+            return Err(io::Error::new(kind, "not a directory"));
+        }
+        Ok(children(&self.dirs, &path).chain(children(&self.files, &path))
+            .filter_map(|p| p.file_name())
+            .map(|name| Ok(name.to_os_string()))
+            .collect())
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dirs.contains(&normalize(path))
+    }
+    fn exists(&self, path: &Path) -> bool {
+        let path = normalize(path);
+        self.dirs.contains(&path) || self.files.contains(&path)
+    }
+}
+
+// Paths are ordered by their components, so everything inside of the
+// directory comes right after it
+fn children<'a>(set: &'a BTreeSet<PathBuf>, dir: &'a Path)
+    -> impl Iterator<Item=&'a PathBuf> + 'a
+{
+    set.range::<Path, _>((Bound::Included(dir), Bound::Unbounded))
+        .take_while(move |p| p.starts_with(dir))
+        .filter(move |p| p.parent() == Some(dir))
+}
+
+// Removes `.` and resolves `..` components, the current directory is an
+// empty path
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            c => result.push(c.as_os_str()),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use super::{FileSystem, MemoryFs};
+
+    #[test]
+    fn memory() {
+        let mut fs = MemoryFs::new();
+        fs.add_file("a/b/c.txt").add_file("/etc/hosts");
+        fs.add_file("a/b.txt").add_file("a/b2/d.txt").add_file("b");
+        assert!(fs.is_dir(Path::new(".")));
+        assert!(fs.is_dir(Path::new("a/b")));
+        assert!(fs.is_dir(Path::new("a/b/..")));
+        assert!(fs.is_dir(Path::new("/")));
+        assert!(!fs.is_dir(Path::new("a/b/c.txt")));
+        assert!(fs.exists(Path::new("./a/b/c.txt")));
+        assert!(!fs.exists(Path::new("a/c.txt")));
+        let names = |p: &str| {
+            fs.read_dir(Path::new(p)).unwrap().into_iter()
+                .map(|n| n.unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("."), ["a", "b"]);
+        assert_eq!(names("/"), ["etc"]);
+        assert_eq!(names("a"), ["b", "b2", "b.txt"]);
+        assert_eq!(names("a/b"), ["c.txt"]);
+        assert!(fs.read_dir(Path::new("a/b/c.txt")).is_err());
+        assert!(fs.read_dir(Path::new("x")).is_err());
+    }
+}
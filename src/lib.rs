@@ -45,12 +45,15 @@
 //!
 //! ```rust,no_run
 //! use capturing_glob::glob_with;
-//! use capturing_glob::MatchOptions;
+//! use capturing_glob::{MatchOptions, Separator};
 //!
 //! let options = MatchOptions {
 //!     case_sensitive: false,
 //!     require_literal_separator: false,
 //!     require_literal_leading_dot: false,
+//!     normalization_insensitive: false,
+//!     unicode_classes: false,
+//!     separator: Separator::Platform,
 //! };
 //! for entry in glob_with("local/*a*", &options).unwrap() {
 //!     if let Ok(entry) = entry {
@@ -98,20 +101,55 @@
 #![deny(missing_debug_implementations)]
 #![cfg_attr(all(test, windows), feature(std_misc))]
 
-mod entry;
+extern crate unicode_normalization;
+#[cfg(unix)] extern crate libc;
+#[cfg(feature = "tar")] extern crate tar;
+#[cfg(feature = "tar")] extern crate flate2;
+#[cfg(feature = "zip")] extern crate zip;
+#[cfg(all(feature = "watch", target_os = "linux"))] extern crate inotify;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(feature = "serde")] #[macro_use] extern crate serde_derive;
 
-pub use entry::Entry;
+mod entry;
+mod normalize;
+mod automaton;
+mod specificity;
+mod filesystem;
+mod progress;
+mod snapshot;
+mod rename;
+mod template;
+mod typed;
+#[cfg(feature = "serde")] mod serde_impl;
+#[cfg(all(feature = "watch", target_os = "linux"))] mod watch;
+#[cfg(any(feature = "tar", feature = "zip"))] mod archive;
+pub mod ast;
+
+pub use entry::{Entry, CaptureSpans, KeyCaptures};
+pub use filesystem::{FileSystem, StdFs, MemoryFs};
+pub use progress::{Progress, CancelToken};
+pub use snapshot::{GlobSnapshot, SnapshotEntry, SnapshotDiff, SnapshotError, diff};
+pub use rename::{Rename, RenamePlan, RenameError, plan_rename};
+pub use template::{Template, TemplateError};
+pub use typed::{CaptureError, FromCaptures, FromGlob, GlobValues, FromGlobError};
+#[cfg(feature = "tar")] pub use archive::TarArchive;
+#[cfg(feature = "zip")] pub use archive::ZipArchive;
+#[cfg(all(feature = "watch", target_os = "linux"))]
+pub use watch::{watch, Watcher, WatchEvent};
+pub use specificity::{Specificity, best_match, best_match_with};
 
-use std::ascii::AsciiExt;
 use std::cmp;
 use std::fmt;
-use std::fs;
 use std::io;
 use std::path::{self, Path, PathBuf, Component};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::error::Error;
 
-use CharSpecifier::{SingleChar, CharRange};
+use normalize::Normalized;
+use progress::ProgressCallback;
+use ast::{CharClass, ExtGlobKind};
+use CharSpecifier::{SingleChar, CharRange, Class};
 use MatchResult::{Match, SubPatternDoesntMatch, EntirePatternDoesntMatch};
 
 /// An iterator that yields Entry'ies that match a particular pattern.
@@ -123,15 +161,24 @@ use MatchResult::{Match, SubPatternDoesntMatch, EntirePatternDoesntMatch};
 /// thereby preventing its contents from being checked for matches, a
 /// `GlobError` is returned to express this.
 ///
+/// Directories are read using `F`, which is the real file system by
+/// default, see `glob_in` for globbing elsewhere.
+///
 /// See the `glob` function for more details.
 #[derive(Debug)]
-pub struct Entries {
+pub struct Entries<F = StdFs> {
+    fs: F,
     whole_pattern: Pattern,
     dir_patterns: Vec<Pattern>,
     require_dir: bool,
     options: MatchOptions,
     todo: Vec<Result<(PathBuf, usize), GlobError>>,
     scope: Option<PathBuf>,
+    progress: Progress,
+    on_progress: Option<ProgressCallback>,
+    cancel: Option<CancelToken>,
+    error_policy: ErrorPolicy,
+    errors: Vec<GlobError>,
 }
 
 /// Return an iterator that produces all the paths and capture groups that
@@ -214,6 +261,44 @@ pub fn glob(pattern: &str) -> Result<Entries, PatternError> {
 /// Entries are yielded in alphabetical order.
 pub fn glob_with(pattern: &str, options: &MatchOptions)
                  -> Result<Entries, PatternError> {
+    glob_with_options(pattern, &PatternOptions::new(), options)
+}
+
+/// Return an iterator that produces all the paths with capture groups that
+/// match the given pattern, compiled with the specified syntax options and
+/// matched using the specified match options.
+///
+/// This may return an error if the pattern is invalid.
+///
+/// See `glob_with(..)` for the details on how options are applied.
+pub fn glob_with_options(pattern: &str, syntax: &PatternOptions,
+                         options: &MatchOptions)
+                         -> Result<Entries, PatternError> {
+    glob_in_with_options(StdFs, pattern, syntax, options)
+}
+
+/// Return an iterator that produces all the paths with capture groups that
+/// match the given pattern in the specified file system using the specified
+/// match options
+///
+/// This works exactly like `glob_with(..)`, except that directories are
+/// read with `fs` rather than with `std::fs`, so it's possible to glob in an
+/// archive, a version control tree or a `MemoryFs` in tests. Pass the file
+/// system by reference to keep using it afterwards.
+pub fn glob_in<F: FileSystem>(fs: F, pattern: &str, options: &MatchOptions)
+    -> Result<Entries<F>, PatternError>
+{
+    glob_in_with_options(fs, pattern, &PatternOptions::new(), options)
+}
+
+/// Return an iterator that produces all the paths with capture groups that
+/// match the given pattern in the specified file system, compiled with the
+/// specified syntax options and matched using the specified match options
+pub fn glob_in_with_options<F: FileSystem>(fs: F, pattern: &str,
+                                           syntax: &PatternOptions,
+                                           options: &MatchOptions)
+    -> Result<Entries<F>, PatternError>
+{
     let last_is_separator = pattern.chars().next_back().map(path::is_separator);
     let require_dir = last_is_separator == Some(true);
 
@@ -232,7 +317,7 @@ pub fn glob_with(pattern: &str, options: &MatchOptions)
         txt = &txt[2..];
     }
     // TODO(tailhook) This may mess up error offsets
-    let compiled = Pattern::new(txt)?;
+    let compiled = Pattern::new_with(txt, syntax)?;
 
     #[cfg(windows)]
     fn check_windows_verbatim(p: &Path) -> bool {
@@ -258,14 +343,10 @@ pub fn glob_with(pattern: &str, options: &MatchOptions)
     }
 
     let mut components = Path::new(pattern).components().peekable();
-    loop {
-        match components.peek() {
-            Some(&Component::Prefix(..)) |
-            Some(&Component::RootDir) => {
-                components.next();
-            }
-            _ => break,
-        }
+    while let Some(&Component::Prefix(..)) |
+              Some(&Component::RootDir) = components.peek()
+    {
+        components.next();
     }
     let rest = components.map(|s| s.as_os_str()).collect::<PathBuf>();
     let normalized_pattern = Path::new(pattern).iter().collect::<PathBuf>();
@@ -281,12 +362,18 @@ pub fn glob_with(pattern: &str, options: &MatchOptions)
         // return nothing, since we can't very well find all UNC shares with a
         // 1-letter server name.
         return Ok(Entries {
+            fs,
             dir_patterns: Vec::new(),
             whole_pattern: compiled,
             require_dir: false,
             options: options.clone(),
             todo: Vec::new(),
             scope: None,
+            progress: Progress::default(),
+            on_progress: None,
+            cancel: None,
+            error_policy: ErrorPolicy::Yield,
+            errors: Vec::new(),
         });
     }
 
@@ -297,7 +384,7 @@ pub fn glob_with(pattern: &str, options: &MatchOptions)
                          .split_terminator(path::is_separator);
 
     for component in components {
-        let compiled = Pattern::new_options(component, true)?;
+        let compiled = Pattern::new_options(component, true, syntax)?;
         dir_patterns.push(compiled);
     }
 
@@ -306,18 +393,26 @@ pub fn glob_with(pattern: &str, options: &MatchOptions)
             original: "".to_string(),
             tokens: Vec::new(),
             is_recursive: false,
+            names: Arc::from(Vec::new()),
+            normalized: None,
         });
     }
 
     let todo = Vec::new();
 
     Ok(Entries {
-        dir_patterns: dir_patterns,
+        fs,
+        dir_patterns,
         whole_pattern: compiled,
-        require_dir: require_dir,
+        require_dir,
         options: options.clone(),
-        todo: todo,
+        todo,
         scope: Some(scope),
+        progress: Progress::default(),
+        on_progress: None,
+        cancel: None,
+        error_policy: ErrorPolicy::Yield,
+        errors: Vec::new(),
     })
 }
 
@@ -330,9 +425,77 @@ pub fn glob_with(pattern: &str, options: &MatchOptions)
 pub struct GlobError {
     path: PathBuf,
     error: io::Error,
+    kind: GlobErrorKind,
+    depth: usize,
+    component: String,
+}
+
+/// The cause of a `GlobError`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GlobErrorKind {
+    /// The directory can't be read because of the permissions
+    PermissionDenied,
+    /// The directory was removed or replaced by a file after its parent
+    /// directory was read, typically a race with another process. The
+    /// directory the walk starts from is never reported as vanished.
+    Vanished,
+    /// Symbolic links form a loop, or are nested too deep
+    Loop,
+    /// The walk was stopped with a `CancelToken`
+    Cancelled,
+    /// Any other I/O error
+    Other,
+}
+
+impl GlobErrorKind {
+    // Only a directory that was found during the walk can vanish, the one
+    // the walk starts from might have never existed
+    fn of(error: &io::Error, seen: bool) -> GlobErrorKind {
+        #[cfg(unix)]
+        let (not_dir, is_loop) = (
+            error.raw_os_error() == Some(libc::ENOTDIR),
+            error.raw_os_error() == Some(libc::ELOOP),
+        );
+        #[cfg(not(unix))]
+        let (not_dir, is_loop) = (false, false);
+
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => GlobErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound if seen => GlobErrorKind::Vanished,
+            _ if not_dir && seen => GlobErrorKind::Vanished,
+            _ if is_loop => GlobErrorKind::Loop,
+            _ => GlobErrorKind::Other,
+        }
+    }
 }
 
 impl GlobError {
+    fn new(path: PathBuf, error: io::Error, depth: usize, component: &Pattern,
+           seen: bool)
+        -> GlobError
+    {
+        GlobError {
+            path,
+            kind: GlobErrorKind::of(&error, seen),
+            error,
+            depth,
+            component: component.as_str().to_string(),
+        }
+    }
+
+    fn cancelled(path: PathBuf, depth: usize, component: &Pattern)
+        -> GlobError
+    {
+        GlobError {
+            path,
+            error: io::Error::new(io::ErrorKind::Interrupted, "glob cancelled"),
+            kind: GlobErrorKind::Cancelled,
+            depth,
+            component: component.as_str().to_string(),
+        }
+    }
+
     /// The Path that the error corresponds to.
     pub fn path(&self) -> &Path {
         &self.path
@@ -342,19 +505,50 @@ impl GlobError {
     pub fn error(&self) -> &io::Error {
         &self.error
     }
+
+    /// The cause of the error
+    pub fn kind(&self) -> GlobErrorKind {
+        self.kind
+    }
+
+    /// Return true if the error was caused by `CancelToken::cancel()`
+    /// rather than by the file system
+    pub fn is_cancelled(&self) -> bool {
+        self.kind == GlobErrorKind::Cancelled
+    }
+
+    /// The number of components of the pattern matched by the path, not
+    /// counting the root directory
+    ///
+    /// For example, for the pattern `/var/*/log/*.log` the error for
+    /// `/var/cache/log` has depth `3`.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The component of the pattern that the contents of the directory were
+    /// going to be matched against, e.g. `*.log` in the example above
+    pub fn component(&self) -> &str {
+        &self.component
+    }
 }
 
 impl Error for GlobError {
-    fn description(&self) -> &str {
-        self.error.description()
-    }
-    fn cause(&self) -> Option<&Error> {
-        Some(&self.error)
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        if self.is_cancelled() {
+            None
+        } else {
+            Some(&self.error)
+        }
     }
 }
 
 impl fmt::Display for GlobError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_cancelled() {
+            return write!(f, "glob cancelled before reading `{}`",
+                          self.path.display());
+        }
         write!(f,
                "attempting to read `{}` resulted in an error: {}",
                self.path.display(),
@@ -362,8 +556,21 @@ impl fmt::Display for GlobError {
     }
 }
 
-fn is_dir(p: &Path) -> bool {
-    fs::metadata(p).map(|m| m.is_dir()).unwrap_or(false)
+/// What `Entries` does with the errors of reading directories
+///
+/// Cancellation (see `CancelToken`) is always reported by yielding an
+/// error, regardless of the policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorPolicy {
+    /// Yield errors along with the matches
+    #[default]
+    Yield,
+    /// Ignore errors
+    Skip,
+    /// Keep errors in a list that can be read with `Entries::errors()`
+    Collect,
+    /// Yield the first error and stop
+    Abort,
 }
 
 /// An alias for a glob iteration result.
@@ -372,24 +579,154 @@ fn is_dir(p: &Path) -> bool {
 /// such as failing to read a particular directory's contents.
 pub type GlobResult = Result<Entry, GlobError>;
 
-impl Iterator for Entries {
+impl<F> Entries<F> {
+    /// Call `callback` with the statistics of the walk every time a
+    /// directory is read or a match is found
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::glob;
+    ///
+    /// let entries = glob("src/**/*.rs").unwrap()
+    ///     .on_progress(|p| eprint!("\r{} dirs, {} files, {} matches",
+    ///                              p.dirs_read, p.entries_examined, p.matches));
+    /// assert!(entries.count() > 0);
+    /// ```
+    pub fn on_progress<C>(mut self, callback: C) -> Entries<F>
+        where C: FnMut(&Progress) + Send + 'static,
+    {
+        self.on_progress = Some(ProgressCallback(Box::new(callback)));
+        self
+    }
+
+    /// Stop the walk when the token is cancelled
+    ///
+    /// The token is checked before reading every directory, once it's
+    /// cancelled the iterator yields a `GlobError` for which
+    /// `is_cancelled()` returns true, and then ends.
+    pub fn cancel_token(mut self, token: CancelToken) -> Entries<F> {
+        self.cancel = Some(token);
+        self
+    }
+
+    /// Set what to do with the errors of reading directories, by default
+    /// they are yielded along with the matches
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::{glob, ErrorPolicy};
+    ///
+    /// let mut entries = glob("/proc/*/fd/*").unwrap()
+    ///     .error_policy(ErrorPolicy::Collect);
+    /// for entry in entries.by_ref() {
+    ///     println!("{}", entry.unwrap().path().display());
+    /// }
+    /// for error in entries.errors() {
+    ///     eprintln!("{}", error);
+    /// }
+    /// ```
+    pub fn error_policy(mut self, policy: ErrorPolicy) -> Entries<F> {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Return the errors collected so far with `ErrorPolicy::Collect`
+    pub fn errors(&self) -> &[GlobError] {
+        &self.errors
+    }
+
+    /// Remove and return the errors collected so far with
+    /// `ErrorPolicy::Collect`
+    pub fn take_errors(&mut self) -> Vec<GlobError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Return the statistics of the walk so far
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    fn report(&mut self) {
+        if let Some(ref mut callback) = self.on_progress {
+            (callback.0)(&self.progress);
+        }
+    }
+
+    // Drops the rest of the work if the walk is cancelled
+    fn check_cancelled(&mut self, next_path: Option<&Path>)
+        -> Option<GlobError>
+    {
+        if !self.cancel.as_ref().is_some_and(|t| t.is_cancelled()) {
+            return None;
+        }
+        let (path, depth) = match (next_path, self.todo.last()) {
+            (Some(path), _) => (path.to_path_buf(), 0),
+            (None, Some(Ok((path, idx)))) => (path.clone(), *idx),
+            (None, Some(Err(e))) => (e.path.clone(), e.depth),
+            (None, None) => (PathBuf::new(), 0),
+        };
+        let depth = cmp::min(depth, self.dir_patterns.len() - 1);
+        let error = GlobError::cancelled(path, depth, &self.dir_patterns[depth]);
+        self.todo.clear();
+        self.dir_patterns.clear();
+        Some(error)
+    }
+}
+
+impl<F: FileSystem> Iterator for Entries<F> {
     type Item = GlobResult;
 
     fn next(&mut self) -> Option<GlobResult> {
+        loop {
+            match self.walk() {
+                Some(Ok(entry)) => {
+                    self.progress.matches += 1;
+                    self.report();
+                    return Some(Ok(entry));
+                }
+                Some(Err(e)) if !e.is_cancelled() => {
+                    match self.error_policy {
+                        ErrorPolicy::Yield => return Some(Err(e)),
+                        ErrorPolicy::Skip => {}
+                        ErrorPolicy::Collect => self.errors.push(e),
+                        ErrorPolicy::Abort => {
+                            self.todo.clear();
+                            self.dir_patterns.clear();
+                            return Some(Err(e));
+                        }
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<F: FileSystem> Entries<F> {
+    fn walk(&mut self) -> Option<GlobResult> {
         // the todo buffer hasn't been initialized yet, so it's done at this
         // point rather than in glob() so that the errors are unified that is,
         // failing to fill the buffer is an iteration error construction of the
         // iterator (i.e. glob()) only fails if it fails to compile the Pattern
         if let Some(scope) = self.scope.take() {
-            if self.dir_patterns.len() > 0 {
+            if !self.dir_patterns.is_empty() {
                 // Shouldn't happen, but we're using -1 as a special index.
-                assert!(self.dir_patterns.len() < !0 as usize);
+                assert!(self.dir_patterns.len() < !0);
 
-                fill_todo(&mut self.todo,
+                if let Some(e) = self.check_cancelled(Some(&scope)) {
+                    return Some(Err(e));
+                }
+                fill_todo(&self.fs,
+                          &mut self.progress,
+                          &mut self.todo,
                           &self.dir_patterns,
                           0,
                           &scope,
+                          false,
                           &self.options);
+                self.report();
             }
         }
 
@@ -397,7 +734,11 @@ impl Iterator for Entries {
             if self.dir_patterns.is_empty() || self.todo.is_empty() {
                 return None;
             }
+            if let Some(e) = self.check_cancelled(None) {
+                return Some(Err(e));
+            }
 
+            self.progress.entries_examined += 1;
             let (path, mut idx) = match self.todo.pop().unwrap() {
                 Ok(pair) => pair,
                 Err(e) => return Some(Err(e)),
@@ -405,8 +746,8 @@ impl Iterator for Entries {
 
             // idx -1: was already checked by fill_todo, maybe path was '.' or
             // '..' that we can't match here because of normalization.
-            if idx == !0 as usize {
-                if self.require_dir && !is_dir(&path) {
+            if idx == !0 {
+                if self.require_dir && !self.fs.is_dir(&path) {
                     continue;
                 }
                 return Some(Ok(Entry::new(path)));
@@ -421,15 +762,19 @@ impl Iterator for Entries {
                     next += 1;
                 }
 
-                if is_dir(&path) {
+                if self.fs.is_dir(&path) {
                     // the path is a directory, so it's a match
 
                     // push this directory's contents
-                    fill_todo(&mut self.todo,
+                    fill_todo(&self.fs,
+                              &mut self.progress,
+                              &mut self.todo,
                               &self.dir_patterns,
                               next,
                               &path,
+                              true,
                               &self.options);
+                    self.report();
 
                     if next == self.dir_patterns.len() - 1 {
                         // pattern ends in recursive pattern, so return this
@@ -464,15 +809,17 @@ impl Iterator for Entries {
                     // *AND* its children so we don't need to check the
                     // children
 
-                    if !self.require_dir || is_dir(&path) {
+                    if !self.require_dir || self.fs.is_dir(&path) {
                         let entry = self.whole_pattern
                             .captures_path_with(&path, &self.options)
                             .expect("dir patterns consistent with whole pat");
                         return Some(Ok(entry));
                     }
                 } else {
-                    fill_todo(&mut self.todo, &self.dir_patterns,
-                              idx + 1, &path, &self.options);
+                    fill_todo(&self.fs, &mut self.progress, &mut self.todo,
+                              &self.dir_patterns, idx + 1, &path, true,
+                              &self.options);
+                    self.report();
                 }
             }
         }
@@ -508,11 +855,16 @@ impl fmt::Display for PatternError {
 /// A pattern substitution error
 #[derive(Debug)]
 #[allow(missing_copy_implementations)]
+#[non_exhaustive]
 pub enum SubstitutionError {
     /// No value supplied for capture group
     MissingGroup(usize),
     /// Wildcard char `*?[..]` is outside of the capture group
     UnexpectedWildcard,
+    /// Value supplied for capture group is not within its numeric range
+    InvalidValue(usize),
+    /// Pattern expands to more paths than the limit
+    TooManyPaths(usize),
 }
 
 impl Error for SubstitutionError {
@@ -531,6 +883,12 @@ impl fmt::Display for SubstitutionError {
             UnexpectedWildcard => {
                 write!(f, "unexpected wildcard")
             }
+            InvalidValue(g) => {
+                write!(f, "substitution error: invalid value for group {}", g)
+            }
+            TooManyPaths(limit) => {
+                write!(f, "pattern expands to more than {} paths", limit)
+            }
         }
     }
 }
@@ -554,23 +912,95 @@ impl fmt::Display for SubstitutionError {
 /// - `[!...]` is the negation of `[...]`, i.e. it matches any characters
 ///   **not** in the brackets.
 ///
-/// - The metacharacters `?`, `*`, `[`, `]` can be matched by using brackets
-///   (e.g. `[?]`).  When a `]` occurs immediately following `[` or `[!` then it
-///   is interpreted as being part of, rather then ending, the character set, so
-///   `]` and NOT `]` can be matched by `[]]` and `[!]]` respectively.  The `-`
+/// - `[[:alpha:]]` and the other POSIX character classes (`alnum`, `blank`,
+///   `cntrl`, `digit`, `graph`, `lower`, `print`, `punct`, `space`, `upper`,
+///   `xdigit`) can be used inside of the brackets, alone or mixed with other
+///   characters and ranges, e.g. `[[:digit:]_-]`. Classes only match ASCII
+///   characters unless `MatchOptions::unicode_classes` is set (`digit` and
+///   `xdigit` are ASCII-only either way). An unknown class name is invalid.
+///
+/// - `(...)` is a capture group, groups are numbered by their opening
+///   parens starting from one. `(?<name>...)` is a named group, its number
+///   can be looked up with `Pattern::group_index(..)`. A group starting with
+///   `?<` that should match them literally has to be written as `([?]<...)`.
+///
+/// - The metacharacters `?`, `*`, `[`, `]`, `(`, `)`, `{` can be matched by
+///   using brackets (e.g. `[?]`).  When a `]` occurs immediately following
+///   `[` or `[!` then it is interpreted as being part of, rather then ending,
+///   the character set, so `]` and NOT `]` can be matched by `[]]` and `[!]]`
+///   respectively.  The `-`
 ///   character can be specified inside a character sequence pattern by placing
 ///   it at the start or the end, e.g. `[abc-]`.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+///
+/// - `{1..20}` matches any number in the range, written in decimal without
+///   leading zeros. If either of the bounds has a leading zero, e.g.
+///   `{00..63}`, the numbers must be zero-padded to the width of the longer
+///   bound. Ranges are recognized only when the pattern is compiled with
+///   `PatternOptions::braces` set.
+///
+/// - `{a,b}` matches any of the comma-separated alternatives, which may
+///   contain wildcards (except `**` and path separators) and nested braces.
+///   Unlike parens, braces are not capture groups, use `({a,b})` to capture
+///   the alternative. Braces that form neither a range nor alternatives are
+///   matched literally. Like ranges, alternatives are recognized only when
+///   the pattern is compiled with `PatternOptions::braces` set.
+///
+/// - `?(a|b)`, `*(a|b)`, `+(a|b)`, `@(a|b)` and `!(a|b)` are ksh-style
+///   extended glob operators, recognized only when the pattern is compiled
+///   with `PatternOptions::extglob` set. Each of them is a capture group
+///   numbered by its opening paren, like a plain `(...)`. Alternatives may
+///   contain any wildcards except `**` and path separators.
+///
+/// Patterns are ordered by their `specificity()`, so the greatest pattern is
+/// the most specific one. Equally specific patterns are ordered by their
+/// source strings.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct Pattern {
     original: String,
     tokens: Vec<PatternToken>,
     is_recursive: bool,
+    // names of the capture groups, indexed by 0-based group number, shared
+    // with the entries
+    names: Arc<[Option<String>]>,
+    // NFC version of the pattern if it differs from the `tokens`
+    normalized: Option<Box<Pattern>>,
+}
+
+// `Default` for `Arc<[T]>` requires Rust 1.80
+impl Default for Pattern {
+    fn default() -> Pattern {
+        Pattern {
+            original: String::new(),
+            tokens: Vec::new(),
+            is_recursive: false,
+            names: Arc::from(Vec::new()),
+            normalized: None,
+        }
+    }
 }
 
-/// Show the original glob pattern.
+/// Show the glob pattern in the canonical form, use `as_str()` to get the
+/// original one.
 impl fmt::Display for Pattern {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.original.fmt(f)
+        match ast::render(&ast::from_internal(&self.tokens, &self.names)) {
+            Ok(canonical) => canonical.fmt(f),
+            Err(_) => self.original.fmt(f),
+        }
+    }
+}
+
+impl PartialOrd for Pattern {
+    fn partial_cmp(&self, other: &Pattern) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pattern {
+    fn cmp(&self, other: &Pattern) -> cmp::Ordering {
+        self.specificity().cmp(&other.specificity())
+            .then_with(|| self.original.cmp(&other.original))
+            .then_with(|| self.tokens.cmp(&other.tokens))
     }
 }
 
@@ -592,12 +1022,41 @@ enum PatternToken {
     AnyExcept(Vec<CharSpecifier>),
     StartCapture(usize, bool),
     EndCapture(usize, bool),
+    ExtGlob(ExtGlobKind, Vec<Vec<PatternToken>>, Option<usize>),
+    // first and last number as written, and the width to pad numbers to
+    NumRange(u64, u64, usize),
+}
+
+impl ExtGlobKind {
+    fn from_char(c: char) -> Option<ExtGlobKind> {
+        use self::ExtGlobKind::*;
+        match c {
+            '?' => Some(ZeroOrOne),
+            '*' => Some(ZeroOrMore),
+            '+' => Some(OneOrMore),
+            '@' => Some(ExactlyOne),
+            '!' => Some(Not),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        use self::ExtGlobKind::*;
+        match self {
+            ZeroOrOne => '?',
+            ZeroOrMore => '*',
+            OneOrMore => '+',
+            ExactlyOne => '@',
+            Not => '!',
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 enum CharSpecifier {
     SingleChar(char),
     CharRange(char, char),
+    Class(CharClass),
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -614,22 +1073,57 @@ enum CaptureResult {
     EntirePatternDoesntMatch,
 }
 
-const ERROR_WILDCARDS: &'static str = "wildcards are either regular `*` or recursive `**`";
-const ERROR_RECURSIVE_WILDCARDS: &'static str = "recursive wildcards must form a single path \
-                                                 component";
-const ERROR_INVALID_RANGE: &'static str = "invalid range pattern";
+/// The maximum number of paths generated by `Pattern::expand()`
+pub const DEFAULT_EXPAND_LIMIT: usize = 10_000;
+
+const ERROR_WILDCARDS: &str = "wildcards are either regular `*` or recursive `**`";
+const ERROR_RECURSIVE_WILDCARDS: &str = "recursive wildcards must form a single path \
+                                         component";
+const ERROR_INVALID_RANGE: &str = "invalid range pattern";
+const ERROR_INVALID_CLASS: &str = "invalid character class name";
+const ERROR_NESTED_RECURSIVE: &str = "recursive wildcards are not allowed in \
+                                      alternatives";
+const ERROR_NESTED_SEPARATOR: &str = "path separators are not allowed in \
+                                      alternatives";
 
 fn ends_with_sep(s: &[char]) -> bool {
-    for &c in s.iter().rev() {
-        if c == '(' || c == ')' {
-            continue;
-        } else if path::is_separator(c) {
-            return true;
-        } else {
-            return false;
+    let mut end = s.len();
+    while end > 0 {
+        match s[end - 1] {
+            '(' | ')' => end -= 1,
+            '>' => {
+                // skip the `(?<name>` of a named group
+                let start = s[..end - 1].iter()
+                    .rposition(|&c| c == '<')
+                    .filter(|&lt| lt >= 2 && s[lt - 2] == '(' && s[lt - 1] == '?');
+                match start {
+                    Some(lt) if group_name(s, lt - 1, end).is_some() => {
+                        end = lt - 2;
+                    }
+                    _ => return false,
+                }
+            }
+            c => return path::is_separator(c),
         }
     }
-    return true;
+    true
+}
+
+// Parses the `?<name>` part of a named group at `start` (right after the
+// paren), returning the name and the position after the `>`
+fn group_name(chars: &[char], start: usize, end: usize)
+    -> Option<(String, usize)>
+{
+    if start + 1 >= end || chars[start] != '?' || chars[start + 1] != '<' {
+        return None;
+    }
+    let close = start + 2 + chars[start + 2..end].iter().position(|&c| c == '>')?;
+    let name = chars[start + 2..close].iter().collect::<String>();
+    if ast::is_group_name(&name) {
+        Some((name, close + 1))
+    } else {
+        None
+    }
 }
 
 impl Pattern {
@@ -637,234 +1131,450 @@ impl Pattern {
     ///
     /// An invalid glob pattern will yield a `PatternError`.
     pub fn new(pattern: &str) -> Result<Pattern, PatternError> {
-        Pattern::new_options(pattern, false)
+        Pattern::new_options(pattern, false, &PatternOptions::new())
+    }
+
+    /// Compiles a pattern using the specified syntax options.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::{Pattern, PatternOptions};
+    ///
+    /// let options = PatternOptions {
+    ///     extglob: true,
+    ///     .. PatternOptions::new()
+    /// };
+    /// let pattern = Pattern::new_with("(*).@(jpg|png)", &options).unwrap();
+    /// let entry = pattern.captures("cat.png").unwrap();
+    /// assert_eq!(entry.group(1).unwrap(), "cat");
+    /// assert_eq!(entry.group(2).unwrap(), "png");
+    /// assert!(!pattern.matches("cat.gif"));
+    /// ```
+    pub fn new_with(pattern: &str, options: &PatternOptions)
+        -> Result<Pattern, PatternError>
+    {
+        Pattern::new_options(pattern, false, options)
     }
     /// The `skip_groups` of `true` is needed to compile partial patterns in
     /// glob directory scanner
-    fn new_options(pattern: &str, skip_groups: bool)
+    fn new_options(pattern: &str, skip_groups: bool, options: &PatternOptions)
         -> Result<Pattern, PatternError>
     {
-        use self::PatternToken::*;
-
         let chars = pattern.chars().collect::<Vec<_>>();
-        let mut tokens = Vec::new();
-        let mut is_recursive = false;
-        let mut i = 0;
-        let mut last_capture = 0;
-        let mut captures_stack = Vec::new();
+        let mut parser = Parser {
+            chars: &chars,
+            skip_groups,
+            extglob: options.extglob,
+            braces: options.braces,
+            names: Vec::new(),
+            is_recursive: false,
+        };
+        let tokens = parser.parse(0, chars.len(), false)?;
+        let is_recursive = parser.is_recursive;
+
+        let normalized = normalize::normalize_tokens(&tokens)
+            .map(|tokens| Box::new(Pattern {
+                tokens,
+                original: pattern.to_string(),
+                is_recursive,
+                names: Arc::from(Vec::new()),
+                normalized: None,
+            }));
 
-        while i < chars.len() {
-            match chars[i] {
-                '?' => {
-                    tokens.push(AnyChar);
-                    i += 1;
+        Ok(Pattern {
+            tokens,
+            original: pattern.to_string(),
+            is_recursive,
+            names: parser.names.into(),
+            normalized,
+        })
+    }
+
+    /// Escape metacharacters within the given string by surrounding them in
+    /// brackets. The resulting string will, when compiled into a `Pattern`,
+    /// match the input string and nothing else.
+    ///
+    /// Parens and braces are escaped too, whether or not braces are enabled
+    /// by `PatternOptions`.
+    pub fn escape(s: &str) -> String {
+        let mut escaped = String::new();
+        for c in s.chars() {
+            match c {
+                // note that ! does not need escaping because it is only special
+                // inside brackets
+                '?' | '*' | '[' | ']' | '(' | ')' | '{' => {
+                    escaped.push('[');
+                    escaped.push(c);
+                    escaped.push(']');
                 }
-                '*' => {
-                    let old = i;
+                c => {
+                    escaped.push(c);
+                }
+            }
+        }
+        escaped
+    }
 
-                    while i < chars.len() && chars[i] == '*' {
-                        i += 1;
-                    }
+    /// Return if the given `str` matches this `Pattern` using the default
+    /// match options (i.e. `MatchOptions::new()`).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// assert!(Pattern::new("c?t").unwrap().matches("cat"));
+    /// assert!(Pattern::new("k[!e]tteh").unwrap().matches("kitteh"));
+    /// assert!(Pattern::new("d*g").unwrap().matches("doog"));
+    /// ```
+    pub fn matches(&self, str: &str) -> bool {
+        self.matches_with(str, &MatchOptions::new())
+    }
 
-                    let count = i - old;
+    /// Return if the given `Path`, when converted to a `str`, matches this
+    /// `Pattern` using the default match options (i.e. `MatchOptions::new()`).
+    pub fn matches_path(&self, path: &Path) -> bool {
+        // FIXME (#9639): This needs to handle non-utf8 paths
+        path.to_str().is_some_and(|s| self.matches(s))
+    }
 
-                    if count > 2 {
-                        return Err(PatternError {
-                            pos: old + 2,
-                            msg: ERROR_WILDCARDS,
-                        });
-                    } else if count == 2 {
-                        // collapse consecutive AnyRecursiveSequence to a
-                        // single one
-                        let tokens_len = tokens.len();
-                        if !(tokens_len > 1 && tokens[tokens_len - 1] == AnyRecursiveSequence) {
-                            is_recursive = true;
-                            tokens.push(AnyRecursiveSequence);
-                        }
-                        // ** can only be an entire path component
-                        // i.e. a/**/b is valid, but a**/b or a/**b is not
-                        // invalid matches are treated literally
-                        if ends_with_sep(&chars[..i - count]) {
-                            // it ends in a '/' sans parenthesis
-                            while i < chars.len() &&
-                                (chars[i] == '(' || chars[i] == ')')
-                            {
-                                if !skip_groups {
-                                    if chars[i] == '(' {
-                                        captures_stack.push((last_capture, i));
-                                        tokens.push(StartCapture(last_capture, true));
-                                        last_capture += 1;
-                                    } else if chars[i] == ')' {
-                                        if let Some((c, _)) = captures_stack.pop()
-                                        {
-                                            tokens.push(EndCapture(c, true));
-                                        } else {
-                                            return Err(PatternError {
-                                                pos: i,
-                                                msg: "Unmatched closing paren",
-                                            });
-                                        }
-                                    }
-                                }
-                                i += 1;
-                            }
-                            if i < chars.len() && path::is_separator(chars[i]) {
-                                i += 1;
-                                // or the pattern ends here
-                                // this enables the existing globbing mechanism
-                            } else if i == chars.len() {
-                                // `**` ends in non-separator
-                            } else {
-                                return Err(PatternError {
-                                    pos: i,
-                                    msg: ERROR_RECURSIVE_WILDCARDS,
-                                });
-                            }
-                            // `**` begins with non-separator
-                        } else {
-                            return Err(PatternError {
-                                pos: old - 1,
-                                msg: ERROR_RECURSIVE_WILDCARDS,
-                            });
-                        }
-                    } else {
-                        tokens.push(AnySequence);
-                    }
-                }
-                '[' => {
+    /// Return if the given `str` matches this `Pattern` using the specified
+    /// match options.
+    pub fn matches_with(&self, str: &str, options: &MatchOptions) -> bool {
+        if options.normalization_insensitive {
+            let norm = Normalized::new(str);
+            let text = norm.as_ref().map_or(str, |n| n.as_str());
+            return Pattern::matches_from(&self.normalized().tokens, true,
+                                         text.chars(), options) == Match;
+        }
+        Pattern::matches_from(&self.tokens, true, str.chars(), options) == Match
+    }
 
-                    if i + 4 <= chars.len() && chars[i + 1] == '!' {
-                        match chars[i + 3..].iter().position(|x| *x == ']') {
-                            None => (),
-                            Some(j) => {
-                                let chars = &chars[i + 2..i + 3 + j];
-                                let cs = parse_char_specifiers(chars);
-                                tokens.push(AnyExcept(cs));
-                                i += j + 4;
-                                continue;
-                            }
-                        }
-                    } else if i + 3 <= chars.len() && chars[i + 1] != '!' {
-                        match chars[i + 2..].iter().position(|x| *x == ']') {
-                            None => (),
-                            Some(j) => {
-                                let cs = parse_char_specifiers(&chars[i + 1..i + 2 + j]);
-                                tokens.push(AnyWithin(cs));
-                                i += j + 3;
-                                continue;
-                            }
-                        }
-                    }
+    /// Return if the given `Path`, when converted to a `str`, matches this
+    /// `Pattern` using the specified match options.
+    pub fn matches_path_with(&self, path: &Path, options: &MatchOptions) -> bool {
+        // FIXME (#9639): This needs to handle non-utf8 paths
+        path.to_str().is_some_and(|s| self.matches_with(s, options))
+    }
 
-                    // if we get here then this is not a valid range pattern
-                    return Err(PatternError {
-                        pos: i,
-                        msg: ERROR_INVALID_RANGE,
-                    });
-                }
-                '(' => {
-                    if !skip_groups {
-                        captures_stack.push((last_capture, i));
-                        tokens.push(StartCapture(last_capture, false));
-                        last_capture += 1;
-                    }
-                    i += 1;
-                }
-                ')' => {
-                    if !skip_groups {
-                        if let Some((c, _)) = captures_stack.pop() {
-                            tokens.push(EndCapture(c, false));
-                        } else {
-                            return Err(PatternError {
-                                pos: i,
-                                msg: "Unmatched closing paren",
-                            });
-                        }
-                    }
-                    i += 1;
-                }
-                c => {
-                    tokens.push(Char(c));
-                    i += 1;
-                }
-            }
-        }
+    /// Access the original glob pattern.
+    pub fn as_str(&self) -> &str {
+        &self.original
+    }
 
-        for (_, i) in captures_stack {
-            return Err(PatternError {
-                pos: i,
-                msg: "Unmatched opening paren",
-            })
-        }
+    /// Return true if the pattern has no wildcards, i.e. it matches a single
+    /// path (capture groups are allowed).
+    pub fn is_literal(&self) -> bool {
+        use self::PatternToken::*;
 
-        Ok(Pattern {
-            tokens: tokens,
-            original: pattern.to_string(),
-            is_recursive: is_recursive,
+        self.tokens.iter().all(|token| {
+            matches!(*token, Char(_) | StartCapture(..) | EndCapture(..))
         })
     }
 
-    /// Escape metacharacters within the given string by surrounding them in
-    /// brackets. The resulting string will, when compiled into a `Pattern`,
-    /// match the input string and nothing else.
-    pub fn escape(s: &str) -> String {
-        let mut escaped = String::new();
-        for c in s.chars() {
-            match c {
-                // note that ! does not need escaping because it is only special
-                // inside brackets
-                '?' | '*' | '[' | ']' => {
-                    escaped.push('[');
-                    escaped.push(c);
-                    escaped.push(']');
-                }
-                c => {
-                    escaped.push(c);
-                }
-            }
+    /// Return the number of capture groups in the pattern
+    ///
+    /// Extended glob operators are counted as groups too, so
+    /// `entry.group(n)` is present for every `n` from `1` to this number.
+    pub fn group_count(&self) -> usize {
+        count_groups(&self.tokens)
+    }
+
+    /// Return the 1-based index of the capture group named `name` using the
+    /// `(?<name>...)` syntax, suitable for `Entry::group(..)`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// let pattern = Pattern::new("(?<dir>*)/(?<name>*).txt").unwrap();
+    /// let entry = pattern.captures("notes/todo.txt").unwrap();
+    /// let idx = pattern.group_index("name").unwrap();
+    /// assert_eq!(entry.group(idx).unwrap(), "todo");
+    /// ```
+    pub fn group_index(&self, name: &str) -> Option<usize> {
+        self.names.iter()
+            .position(|n| n.as_ref().is_some_and(|n| n == name))
+            .map(|n| n + 1)
+    }
+
+    /// Return how specific the pattern is, for picking one of several
+    /// patterns matching the same path
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// let page = Pattern::new("blog/(*).html").unwrap();
+    /// let any = Pattern::new("(**)/(*).html").unwrap();
+    /// assert!(page.specificity() > any.specificity());
+    /// ```
+    pub fn specificity(&self) -> Specificity {
+        Specificity::of(&self.tokens)
+    }
+
+    /// Return an example of a path matched by both patterns using the
+    /// specified match options, or `None` if there is no such path
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::{Pattern, MatchOptions};
+    ///
+    /// let options = MatchOptions {
+    ///     require_literal_separator: true,
+    ///     .. MatchOptions::new()
+    /// };
+    /// let docs = Pattern::new("docs/(*).md").unwrap();
+    /// let index = Pattern::new("docs/(**)/index.md").unwrap();
+    /// let images = Pattern::new("docs/*.png").unwrap();
+    /// assert_eq!(docs.overlaps(&index, &options).unwrap(), "docs/index.md");
+    /// assert_eq!(docs.overlaps(&images, &options), None);
+    /// ```
+    pub fn overlaps(&self, other: &Pattern, options: &MatchOptions)
+        -> Option<String>
+    {
+        let a = automaton::Nfa::new(self.tokens_for(options), options);
+        let b = automaton::Nfa::new(other.tokens_for(options), options);
+        automaton::search(&a, &b, automaton::Goal::Both, |path| {
+            self.matches_with(path, options) && other.matches_with(path, options)
+        })
+    }
+
+    /// Return true if every path matched by this pattern is also matched by
+    /// the `other` one using the default match options, i.e. this pattern
+    /// is made unreachable by the other one if they are tried in order
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// let rust = Pattern::new("src/*.rs").unwrap();
+    /// assert!(Pattern::new("src/lib.rs").unwrap().is_subset_of(&rust));
+    /// assert!(!rust.is_subset_of(&Pattern::new("src/[a-m]*").unwrap()));
+    /// ```
+    pub fn is_subset_of(&self, other: &Pattern) -> bool {
+        self.is_subset_of_with(other, &MatchOptions::new())
+    }
+
+    /// Return true if every path matched by this pattern is also matched by
+    /// the `other` one using the specified match options
+    ///
+    /// The check is exact, but it's slower with case insensitive matching
+    /// or `unicode_classes`, as more characters have to be tried.
+    pub fn is_subset_of_with(&self, other: &Pattern, options: &MatchOptions)
+        -> bool
+    {
+        let a = automaton::Nfa::new(self.tokens_for(options), options);
+        let b = automaton::Nfa::new(other.tokens_for(options), options);
+        let goal = automaton::Goal::FirstOnly;
+        automaton::search(&a, &b, goal, |path| {
+            self.matches_with(path, options) && !other.matches_with(path, options)
+        }).is_none()
+    }
+
+    // The tokens that are used for matching with the options
+    fn tokens_for(&self, options: &MatchOptions) -> &[PatternToken] {
+        if options.normalization_insensitive {
+            &self.normalized().tokens
+        } else {
+            &self.tokens
         }
-        escaped
     }
 
-    /// Return if the given `str` matches this `Pattern` using the default
-    /// match options (i.e. `MatchOptions::new()`).
+    /// Return the tokens of the pattern
     ///
     /// # Examples
     ///
     /// ```rust
     /// use capturing_glob::Pattern;
+    /// use capturing_glob::ast::Token;
     ///
-    /// assert!(Pattern::new("c?t").unwrap().matches("cat"));
-    /// assert!(Pattern::new("k[!e]tteh").unwrap().matches("kitteh"));
-    /// assert!(Pattern::new("d*g").unwrap().matches("doog"));
+    /// assert_eq!(Pattern::new("src/(*).rs").unwrap().tokens(), vec![
+    ///     Token::Literal("src/".into()),
+    ///     Token::Group {
+    ///         index: 1,
+    ///         name: None,
+    ///         tokens: vec![Token::AnySequence],
+    ///     },
+    ///     Token::Literal(".rs".into()),
+    /// ]);
     /// ```
-    pub fn matches(&self, str: &str) -> bool {
-        self.matches_with(str, &MatchOptions::new())
+    pub fn tokens(&self) -> Vec<ast::Token> {
+        ast::from_internal(&self.tokens, &self.names)
     }
 
-    /// Return if the given `Path`, when converted to a `str`, matches this
-    /// `Pattern` using the default match options (i.e. `MatchOptions::new()`).
-    pub fn matches_path(&self, path: &Path) -> bool {
-        // FIXME (#9639): This needs to handle non-utf8 paths
-        path.to_str().map_or(false, |s| self.matches(s))
+    /// Build a pattern from tokens
+    ///
+    /// Literals are escaped as needed. Indexes of capture groups are ignored,
+    /// groups are numbered in the order of their opening parens as usual.
+    /// Extended glob operators are allowed regardless of
+    /// `PatternOptions::extglob`.
+    ///
+    /// The pattern is compiled from its canonical form (as returned by
+    /// `to_string()`), so an invalid sequence of tokens, e.g. `**` which is
+    /// not a whole path component, yields a `PatternError` with the position
+    /// in that string.
+    pub fn from_tokens(tokens: &[ast::Token]) -> Result<Pattern, PatternError> {
+        let canonical = ast::render(tokens)?;
+        let options = PatternOptions {
+            extglob: ast::has_extglob(tokens),
+            braces: true,
+        };
+        Pattern::new_with(&canonical, &options)
     }
 
-    /// Return if the given `str` matches this `Pattern` using the specified
-    /// match options.
-    pub fn matches_with(&self, str: &str, options: &MatchOptions) -> bool {
-        self.matches_from(true, str.chars(), 0, options) == Match
+    /// Return the longest directory all the matched paths are in, judging
+    /// by the leading path components that don't contain any wildcards.
+    ///
+    /// The returned path is empty for patterns that are relative to the
+    /// current directory and start with a wildcard.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::path::Path;
+    /// use capturing_glob::Pattern;
+    ///
+    /// let pattern = Pattern::new("src/(bin)/**/*.rs").unwrap();
+    /// assert_eq!(pattern.literal_prefix(), Path::new("src/bin"));
+    /// assert_eq!(Pattern::new("/a*").unwrap().literal_prefix(), Path::new("/"));
+    /// assert_eq!(Pattern::new("*.rs").unwrap().literal_prefix(), Path::new(""));
+    /// ```
+    pub fn literal_prefix(&self) -> PathBuf {
+        use self::PatternToken::*;
+
+        let mut prefix = String::new();
+        let mut dir_len = 0;
+        for token in &self.tokens {
+            match *token {
+                Char(c) => {
+                    prefix.push(c);
+                    if path::is_separator(c) {
+                        dir_len = prefix.len();
+                    }
+                }
+                StartCapture(..) | EndCapture(..) => {}
+                _ => break,
+            }
+        }
+        let dir = &prefix[..dir_len];
+        let trimmed = dir.trim_end_matches(path::is_separator);
+        if trimmed.is_empty() {
+            // root directory
+            PathBuf::from(dir)
+        } else {
+            PathBuf::from(trimmed)
+        }
     }
 
-    /// Return if the given `Path`, when converted to a `str`, matches this
-    /// `Pattern` using the specified match options.
-    pub fn matches_path_with(&self, path: &Path, options: &MatchOptions) -> bool {
-        // FIXME (#9639): This needs to handle non-utf8 paths
-        path.to_str().map_or(false, |s| self.matches_with(s, options))
+    /// Return the literal text every matched path ends with, i.e. the part
+    /// of the pattern after the last wildcard (the whole pattern if it's
+    /// literal).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// assert_eq!(Pattern::new("**/(*).tar.gz").unwrap().literal_suffix(),
+    ///            ".tar.gz");
+    /// assert_eq!(Pattern::new("*/Cargo.toml").unwrap().literal_suffix(),
+    ///            "/Cargo.toml");
+    /// assert_eq!(Pattern::new("*.[rt]s").unwrap().literal_suffix(), "s");
+    /// ```
+    pub fn literal_suffix(&self) -> String {
+        use self::PatternToken::*;
+
+        let mut suffix = Vec::new();
+        for token in self.tokens.iter().rev() {
+            match *token {
+                Char(c) => suffix.push(c),
+                StartCapture(..) | EndCapture(..) => {}
+                _ => break,
+            }
+        }
+        suffix.iter().rev().collect()
     }
 
-    /// Access the original glob pattern.
-    pub fn as_str<'a>(&'a self) -> &'a str {
-        &self.original
+    /// Return the extension every matched file has, if it's written
+    /// literally in the pattern
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// assert_eq!(Pattern::new("**/*.tar.gz").unwrap().required_extension(),
+    ///            Some("gz".to_string()));
+    /// assert_eq!(Pattern::new("src/*").unwrap().required_extension(), None);
+    /// assert_eq!(Pattern::new("a/.bashrc").unwrap().required_extension(),
+    ///            None);
+    /// ```
+    pub fn required_extension(&self) -> Option<String> {
+        let suffix = self.literal_suffix();
+        let literal_name = self.is_literal() ||
+            suffix.contains(path::is_separator);
+        let name = suffix.rsplit(path::is_separator).next().unwrap_or("");
+        match name.rfind('.') {
+            // a leading dot of a literal name is a hidden file
+            Some(0) if literal_name => None,
+            Some(idx) if idx + 1 < name.len() => {
+                Some(name[idx + 1..].to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the minimum and the maximum number of path components in the
+    /// matched paths, the latter being `None` if the pattern contains `**`.
+    ///
+    /// This assumes that wildcards don't match path separators, which is
+    /// always the case for `glob(..)` (and for `matches_with(..)` with
+    /// `require_literal_separator` set). The root directory is not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// assert_eq!(Pattern::new("/src/*/*.rs").unwrap().depth(), (3, Some(3)));
+    /// assert_eq!(Pattern::new("src/**/*.rs").unwrap().depth(), (2, None));
+    /// ```
+    pub fn depth(&self) -> (usize, Option<usize>) {
+        use self::PatternToken::*;
+
+        let mut count = 0;
+        let mut recursive = false;
+        let mut in_component = false;
+        for token in &self.tokens {
+            match *token {
+                Char(c) if path::is_separator(c) => {
+                    if in_component {
+                        count += 1;
+                    }
+                    in_component = false;
+                }
+                AnyRecursiveSequence => {
+                    // the separator after `**` is a part of the token
+                    if in_component {
+                        count += 1;
+                    }
+                    in_component = false;
+                    recursive = true;
+                }
+                StartCapture(..) | EndCapture(..) => {}
+                _ => in_component = true,
+            }
+        }
+        if in_component {
+            count += 1;
+        }
+        (count, if recursive { None } else { Some(count) })
     }
 
     /// Return entry if filename matches pattern
@@ -892,270 +1602,1030 @@ impl Pattern {
         self.captures_path_with(path, &MatchOptions::new())
     }
 
-    /// Return an entry if filename converted to str matches pattern
-    pub fn captures_path_with(&self, path: &Path, options: &MatchOptions)
-        -> Option<Entry>
+    /// Return an entry if filename converted to str matches pattern
+    pub fn captures_path_with(&self, path: &Path, options: &MatchOptions)
+        -> Option<Entry>
+    {
+        // FIXME (#9639): This needs to handle non-utf8 paths
+        path.to_str().and_then(|s| self.captures_with(s, options))
+    }
+
+    /// Return entry if filename matches pattern
+    pub fn captures_with(&self, str: &str, options: &MatchOptions)
+        -> Option<Entry>
+    {
+        let mut buf = Vec::new();
+        if self.captures_spans(str, &mut buf, options) {
+            Some(Entry::with_captures(str, buf).with_names(&self.names))
+        } else {
+            None
+        }
+    }
+
+    /// Return capture groups if the key matches the pattern
+    ///
+    /// This is meant for strings that are not file system paths, like URL
+    /// paths, object-store keys or archive entries: only `/` is a separator
+    /// regardless of the platform (see `Separator::Slash`), and the key is
+    /// never converted to a `Path`. Other options are the default ones.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// let pattern = Pattern::new("logs/(*)/(*).gz").unwrap();
+    /// let captures = pattern.captures_key("logs/eu/app.gz").unwrap();
+    /// assert_eq!(captures.group(1), Some("eu"));
+    /// assert_eq!(captures.group(2), Some("app"));
+    /// ```
+    pub fn captures_key<'a>(&self, key: &'a str) -> Option<KeyCaptures<'a>> {
+        let options = MatchOptions {
+            separator: Separator::Slash,
+            .. MatchOptions::new()
+        };
+        self.captures_key_with(key, &options)
+    }
+
+    /// Return capture groups if the key matches the pattern using the
+    /// specified options
+    pub fn captures_key_with<'a>(&self, key: &'a str, options: &MatchOptions)
+        -> Option<KeyCaptures<'a>>
+    {
+        let mut spans = CaptureSpans::new();
+        if self.captures_into_with(key, &mut spans, options) {
+            Some(KeyCaptures::new(key, spans))
+        } else {
+            None
+        }
+    }
+
+    /// Match the string and store spans of the capture groups into the
+    /// buffer, returning false if it doesn't match
+    ///
+    /// Unlike `captures()` this doesn't copy the string, and the buffer is
+    /// reused between calls, so in most cases matching doesn't allocate.
+    /// The buffer contents are unspecified if the string doesn't match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::{Pattern, CaptureSpans};
+    ///
+    /// let pattern = Pattern::new("(*)/(*).json").unwrap();
+    /// let mut spans = CaptureSpans::new();
+    /// for key in &["users/alice.json", "groups/admins.json"] {
+    ///     assert!(pattern.captures_into(key, &mut spans));
+    ///     println!("{} of {}", spans.group(key, 2).unwrap(),
+    ///                          spans.group(key, 1).unwrap());
+    /// }
+    /// assert!(!pattern.captures_into("users/bob.txt", &mut spans));
+    /// ```
+    pub fn captures_into(&self, str: &str, spans: &mut CaptureSpans) -> bool {
+        self.captures_into_with(str, spans, &MatchOptions::new())
+    }
+
+    /// Match the string using the specified options and store spans of the
+    /// capture groups into the buffer, returning false if it doesn't match
+    pub fn captures_into_with(&self, str: &str, spans: &mut CaptureSpans,
+                              options: &MatchOptions)
+        -> bool
+    {
+        spans.clear(str.len());
+        self.captures_spans(str, spans.buffer(), options)
+    }
+
+    // Fills the (empty) buffer with spans of all the groups on match
+    fn captures_spans(&self, str: &str, buf: &mut Vec<(usize, usize)>,
+                      options: &MatchOptions)
+        -> bool
+    {
+        use self::CaptureResult::Match;
+        if options.normalization_insensitive {
+            let norm = Normalized::new(str);
+            let text = norm.as_ref().map_or(str, |n| n.as_str());
+            let result = Pattern::captures_from(&self.normalized().tokens, true,
+                text.chars(), text, buf, options);
+            if result != Match(()) {
+                return false;
+            }
+            buf.resize(count_groups(&self.tokens), (0, 0));
+            if let Some(norm) = norm {
+                // spans must point into the original string
+                for span in buf.iter_mut() {
+                    *span = (norm.original_offset(span.0),
+                             norm.original_offset(span.1));
+                }
+            }
+            return true;
+        }
+        let iter = str.chars();
+        match Pattern::captures_from(&self.tokens, true, iter, str, buf,
+                                     options) {
+            Match(()) => {
+                // groups in alternatives that didn't match are empty
+                buf.resize(count_groups(&self.tokens), (0, 0));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The pattern used when `MatchOptions::normalization_insensitive` is set
+    fn normalized(&self) -> &Pattern {
+        self.normalized.as_ref().map_or(self, |p| &**p)
+    }
+
+    fn matches_from(tokens: &[PatternToken],
+                    mut follows_separator: bool,
+                    mut file: std::str::Chars,
+                    options: &MatchOptions)
+                    -> MatchResult
+    {
+        use self::PatternToken::*;
+
+        for (ti, token) in tokens.iter().enumerate() {
+            match *token {
+                AnySequence | AnyRecursiveSequence => {
+                    // ** must be at the start.
+                    debug_assert!(match *token {
+                        AnyRecursiveSequence => follows_separator,
+                        _ => true,
+                    });
+
+                    // Empty match
+                    match Pattern::matches_from(&tokens[ti + 1..], follows_separator,
+                                                file.clone(), options) {
+                        SubPatternDoesntMatch => (), // keep trying
+                        m => return m,
+                    };
+
+                    while let Some(c) = file.next() {
+                        if follows_separator && options.require_literal_leading_dot && c == '.' {
+                            return SubPatternDoesntMatch;
+                        }
+                        follows_separator = options.separator.is_separator(c);
+                        match *token {
+                            AnyRecursiveSequence if !follows_separator => continue,
+                            AnySequence if options.require_literal_separator &&
+                                           follows_separator => return SubPatternDoesntMatch,
+                            _ => (),
+                        }
+                        match Pattern::matches_from(&tokens[ti + 1..],
+                                                    follows_separator,
+                                                    file.clone(),
+                                                    options) {
+                            SubPatternDoesntMatch => (), // keep trying
+                            m => return m,
+                        }
+                    }
+                }
+                StartCapture(..) | EndCapture(..) => {}
+                NumRange(first, last, width) => {
+                    let text = file.as_str();
+                    for end in num_range_ends(text, first, last, width) {
+                        match Pattern::matches_from(&tokens[ti + 1..], false,
+                                                    text[end..].chars(),
+                                                    options) {
+                            SubPatternDoesntMatch => (), // keep trying
+                            m => return m,
+                        }
+                    }
+                    return SubPatternDoesntMatch;
+                }
+                ExtGlob(kind, ref alternatives, _) => {
+                    let text = file.as_str();
+                    for end in extglob_ends(text, options) {
+                        if !match_extglob(kind, alternatives, follows_separator,
+                                          &text[..end], options)
+                        {
+                            continue;
+                        }
+                        match Pattern::matches_from(&tokens[ti + 1..],
+                                                    follows_separator && end == 0,
+                                                    text[end..].chars(),
+                                                    options) {
+                            SubPatternDoesntMatch => (), // keep trying
+                            m => return m,
+                        }
+                    }
+                    return SubPatternDoesntMatch;
+                }
+                _ => {
+                    let c = match file.next() {
+                        Some(c) => c,
+                        None => return EntirePatternDoesntMatch,
+                    };
+
+                    let is_sep = options.separator.is_separator(c);
+
+                    if !match *token {
+                        AnyChar | AnyWithin(..) | AnyExcept(..)
+                            if (options.require_literal_separator && is_sep) ||
+                            (follows_separator && options.require_literal_leading_dot &&
+                             c == '.') => false,
+                        AnyChar => true,
+                        AnyWithin(ref specifiers) => in_char_specifiers(specifiers, c, options),
+                        AnyExcept(ref specifiers) => !in_char_specifiers(specifiers, c, options),
+                        Char(c2) => chars_eq(c, c2, options),
+                        AnySequence | AnyRecursiveSequence => unreachable!(),
+                        StartCapture(..) | EndCapture(..) => unreachable!(),
+                        ExtGlob(..) | NumRange(..) => unreachable!(),
+                    } {
+                        return SubPatternDoesntMatch;
+                    }
+                    follows_separator = is_sep;
+                }
+            }
+        }
+
+        // Iter is fused.
+        if file.next().is_none() {
+            Match
+        } else {
+            SubPatternDoesntMatch
+        }
+    }
+
+    fn captures_from(tokens: &[PatternToken],
+                    mut follows_separator: bool,
+                    mut file: std::str::Chars,
+                    fname: &str,
+                    captures: &mut Vec<(usize, usize)>,
+                    options: &MatchOptions)
+        -> CaptureResult
+    {
+        use self::PatternToken::*;
+        use self::CaptureResult::*;
+
+        for (ti, token) in tokens.iter().enumerate() {
+            match *token {
+                AnySequence | AnyRecursiveSequence => {
+                    // ** must be at the start.
+                    debug_assert!(match *token {
+                        AnyRecursiveSequence => follows_separator,
+                        _ => true,
+                    });
+
+                    // Empty match
+                    match Pattern::captures_from(&tokens[ti + 1..], follows_separator,
+                        file.clone(), fname, captures, options)
+                    {
+                        SubPatternDoesntMatch => (), // keep trying
+                        m => return m,
+                    };
+
+                    while let Some(c) = file.next() {
+                        if follows_separator && options.require_literal_leading_dot && c == '.' {
+                            return SubPatternDoesntMatch;
+                        }
+                        follows_separator = options.separator.is_separator(c);
+                        match *token {
+                            AnyRecursiveSequence if !follows_separator => continue,
+                            AnySequence if options.require_literal_separator &&
+                                           follows_separator => return SubPatternDoesntMatch,
+                            _ => (),
+                        }
+                        match Pattern::captures_from(&tokens[ti + 1..],
+                                                     follows_separator,
+                                                     file.clone(),
+                                                     fname, captures,
+                                                     options) {
+                            SubPatternDoesntMatch => (), // keep trying
+                            m => return m,
+                        }
+                    }
+                }
+                NumRange(first, last, width) => {
+                    let text = file.as_str();
+                    for end in num_range_ends(text, first, last, width) {
+                        match Pattern::captures_from(&tokens[ti + 1..], false,
+                                                     text[end..].chars(),
+                                                     fname, captures,
+                                                     options) {
+                            SubPatternDoesntMatch => (), // keep trying
+                            m => return m,
+                        }
+                    }
+                    return SubPatternDoesntMatch;
+                }
+                ExtGlob(kind, ref alternatives, capture) => {
+                    let text = file.as_str();
+                    let start = offset_in(fname, text);
+                    for end in extglob_ends(text, options) {
+                        let piece = &text[..end];
+                        if !match_extglob(kind, alternatives, follows_separator,
+                                          piece, options)
+                        {
+                            continue;
+                        }
+                        fill_extglob_captures(kind, alternatives,
+                            follows_separator, piece, fname, captures, options);
+                        if let Some(n) = capture {
+                            while captures.len() < n+1 {
+                                captures.push((0, 0));
+                            }
+                            captures[n] = (start, start + end);
+                        }
+                        match Pattern::captures_from(&tokens[ti + 1..],
+                                                     follows_separator && end == 0,
+                                                     text[end..].chars(),
+                                                     fname, captures,
+                                                     options) {
+                            SubPatternDoesntMatch => (), // keep trying
+                            m => return m,
+                        }
+                    }
+                    return SubPatternDoesntMatch;
+                }
+                StartCapture(n, flag) => {
+                    let mut off = offset_in(fname, file.as_str());
+                    if flag {
+                        off -= trailing_separator(&fname[..off], options);
+                    }
+                    while captures.len() < n+1 {
+                        captures.push((0, 0));
+                    }
+                    captures[n] = (off, off);
+                }
+                EndCapture(n, flag) => {
+                    let mut off = offset_in(fname, file.as_str());
+                    if flag {
+                        off -= trailing_separator(&fname[..off], options);
+                    }
+                    if off < captures[n].0 {
+                        // if "a/**/b" matches "a/b"
+                        off = captures[n].0;
+                    }
+                    captures[n].1 = off;
+                }
+                _ => {
+                    let c = match file.next() {
+                        Some(pair) => pair,
+                        None => return EntirePatternDoesntMatch,
+                    };
+
+                    let is_sep = options.separator.is_separator(c);
+
+                    if !match *token {
+                        AnyChar | AnyWithin(..) | AnyExcept(..)
+                            if (options.require_literal_separator && is_sep) ||
+                            (follows_separator && options.require_literal_leading_dot &&
+                             c == '.') => false,
+                        AnyChar => true,
+                        AnyWithin(ref specifiers) => in_char_specifiers(specifiers, c, options),
+                        AnyExcept(ref specifiers) => !in_char_specifiers(specifiers, c, options),
+                        Char(c2) => chars_eq(c, c2, options),
+                        AnySequence | AnyRecursiveSequence => unreachable!(),
+                        StartCapture(..) | EndCapture(..) => unreachable!(),
+                        ExtGlob(..) | NumRange(..) => unreachable!(),
+                    } {
+                        return SubPatternDoesntMatch;
+                    }
+                    follows_separator = is_sep;
+                }
+            }
+        }
+
+        // Iter is fused.
+        if file.next().is_none() {
+            Match(())
+        } else {
+            SubPatternDoesntMatch
+        }
+    }
+    /// Substitute values back into patterns replacing capture groups
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// use capturing_glob::Pattern;
+    ///
+    /// # fn run() -> Result<(), Box<Error>> {
+    /// assert_eq!(Pattern::new("images/(*).jpg")?.substitute(&["cat"])?,
+    ///            "images/cat.jpg");
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    ///
+    /// Note: we check neither result so it matches pattern. The only
+    /// exception are groups containing numeric ranges: a value that is not
+    /// in the range (or not padded the same way) results in an
+    /// `InvalidValue` error.
+    ///
+    /// See `Template` for producing strings that are not patterns, with
+    /// the groups referred to by number or name.
+    pub fn substitute(&self, capture_groups: &[&str])
+        -> Result<String, SubstitutionError>
+    {
+        use self::PatternToken::*;
+
+        let mut result = String::with_capacity(self.original.len());
+        let mut iter = self.tokens.iter();
+        while let Some(tok) = iter.next() {
+            match *tok {
+                Char(c) => result.push(c),
+                AnyChar | AnySequence | AnyRecursiveSequence |
+                AnyWithin(..) | AnyExcept(..) | NumRange(..)
+                => {
+                    return Err(SubstitutionError::UnexpectedWildcard);
+                }
+                StartCapture(idx, _) => {
+                    let val = match capture_groups.get(idx) {
+                        Some(val) => val,
+                        None => return Err(SubstitutionError::MissingGroup(idx)),
+                    };
+                    let group = iter.as_slice();
+                    let len = group.iter()
+                        .position(|tok| *tok == EndCapture(idx, false) ||
+                                        *tok == EndCapture(idx, true))
+                        .unwrap_or(group.len());
+                    let group = &group[..len];
+                    if group.iter().any(|tok| matches!(*tok, NumRange(..))) &&
+                        Pattern::matches_from(group, true, val.chars(),
+                                              &MatchOptions::new()) != Match
+                    {
+                        return Err(SubstitutionError::InvalidValue(idx));
+                    }
+                    result.push_str(val);
+                    for tok in iter.by_ref() {
+                        match *tok {
+                            EndCapture(i, _) if idx == i => break,
+                            _ => {}
+                        }
+                    }
+                }
+                ExtGlob(_, _, Some(idx)) => {
+                    if let Some(val) = capture_groups.get(idx) {
+                        result.push_str(val);
+                    } else {
+                        return Err(SubstitutionError::MissingGroup(idx));
+                    }
+                }
+                ExtGlob(_, _, None) => {
+                    return Err(SubstitutionError::UnexpectedWildcard);
+                }
+                EndCapture(_, _) => unreachable!(),
+            }
+        }
+        Ok(result)
+    }
+
+    /// Expands the pattern into the list of all paths it matches, without
+    /// touching the filesystem
+    ///
+    /// This only works for patterns that have no wildcards except brace
+    /// alternatives, numeric ranges and the `@(..)` and `?(..)` extended glob
+    /// operators, otherwise `UnexpectedWildcard` error is returned. Capture
+    /// groups are ignored. Paths are returned in the order the alternatives
+    /// and numbers are written, and may contain duplicates.
+    ///
+    /// At most `DEFAULT_EXPAND_LIMIT` paths are generated, see
+    /// `expand_limit(..)` for generating more.
+    ///
+    /// ```rust
+    /// # use std::error::Error;
+    /// use capturing_glob::{Pattern, PatternOptions};
+    ///
+    /// # fn run() -> Result<(), Box<Error>> {
+    /// let options = PatternOptions {
+    ///     braces: true,
+    ///     .. PatternOptions::new()
+    /// };
+    /// let pattern = Pattern::new_with("shard-({08..11})/data.bin", &options)?;
+    /// assert_eq!(pattern.expand()?, vec![
+    ///     "shard-08/data.bin",
+    ///     "shard-09/data.bin",
+    ///     "shard-10/data.bin",
+    ///     "shard-11/data.bin",
+    /// ]);
+    /// # Ok(())
+    /// # }
+    /// # fn main() { run().unwrap() }
+    /// ```
+    pub fn expand(&self) -> Result<Vec<String>, SubstitutionError> {
+        self.expand_limit(DEFAULT_EXPAND_LIMIT)
+    }
+
+    /// Same as `expand(..)` but fails with `TooManyPaths` error if the
+    /// pattern matches more than `limit` paths
+    ///
+    /// ```rust
+    /// use capturing_glob::{Pattern, PatternOptions, SubstitutionError};
+    ///
+    /// let options = PatternOptions {
+    ///     braces: true,
+    ///     .. PatternOptions::new()
+    /// };
+    /// let pattern = Pattern::new_with("{src,tests}/{00..99}.rs", &options)
+    ///     .unwrap();
+    /// assert_eq!(pattern.expand_limit(200).unwrap().len(), 200);
+    /// match pattern.expand_limit(100) {
+    ///     Err(SubstitutionError::TooManyPaths(100)) => {}
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn expand_limit(&self, limit: usize)
+        -> Result<Vec<String>, SubstitutionError>
+    {
+        expand_tokens(&self.tokens, limit)
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    skip_groups: bool,
+    extglob: bool,
+    braces: bool,
+    // names of the groups seen so far
+    names: Vec<Option<String>>,
+    is_recursive: bool,
+}
+
+impl<'a> Parser<'a> {
+    // Parses `chars[start..end]`, where `nested` is true for alternatives of
+    // extended glob operators and braces
+    fn parse(&mut self, start: usize, end: usize, nested: bool)
+        -> Result<Vec<PatternToken>, PatternError>
+    {
+        use self::PatternToken::*;
+
+        let mut tokens = Vec::new();
+        let mut i = start;
+        let mut captures_stack = Vec::new();
+
+        while i < end {
+            if self.extglob && i + 1 < end && self.chars[i + 1] == '(' {
+                if let Some(kind) = ExtGlobKind::from_char(self.chars[i]) {
+                    i = self.parse_extglob(kind, i, end, &mut tokens)?;
+                    continue;
+                }
+            }
+            match self.chars[i] {
+                '?' => {
+                    tokens.push(AnyChar);
+                    i += 1;
+                }
+                '*' => {
+                    let old = i;
+
+                    while i < end && self.chars[i] == '*' {
+                        if self.extglob && i + 1 < end && self.chars[i + 1] == '(' {
+                            // `*(` is an extended glob operator
+                            break;
+                        }
+                        i += 1;
+                    }
+
+                    let count = i - old;
+
+                    if count > 2 {
+                        return Err(PatternError {
+                            pos: old + 2,
+                            msg: ERROR_WILDCARDS,
+                        });
+                    } else if count == 2 && nested {
+                        return Err(PatternError {
+                            pos: old,
+                            msg: ERROR_NESTED_RECURSIVE,
+                        });
+                    } else if count == 2 {
+                        // collapse consecutive AnyRecursiveSequence to a
+                        // single one
+                        let tokens_len = tokens.len();
+                        if !(tokens_len > 1 && tokens[tokens_len - 1] == AnyRecursiveSequence) {
+                            self.is_recursive = true;
+                            tokens.push(AnyRecursiveSequence);
+                        }
+                        // ** can only be an entire path component
+                        // i.e. a/**/b is valid, but a**/b or a/**b is not
+                        // invalid matches are treated literally
+                        if ends_with_sep(&self.chars[start..i - count]) {
+                            // it ends in a '/' sans parenthesis
+                            while i < end &&
+                                (self.chars[i] == '(' || self.chars[i] == ')')
+                            {
+                                if !self.skip_groups {
+                                    if self.chars[i] == '(' {
+                                        let n = self.new_group(None);
+                                        captures_stack.push((n, i));
+                                        tokens.push(StartCapture(n, true));
+                                    } else if self.chars[i] == ')' {
+                                        if let Some((c, _)) = captures_stack.pop()
+                                        {
+                                            tokens.push(EndCapture(c, true));
+                                        } else {
+                                            return Err(PatternError {
+                                                pos: i,
+                                                msg: "Unmatched closing paren",
+                                            });
+                                        }
+                                    }
+                                }
+                                i += 1;
+                            }
+                            if i < end && path::is_separator(self.chars[i]) {
+                                i += 1;
+                                // or the pattern ends here
+                                // this enables the existing globbing mechanism
+                            } else if i == end {
+                                // `**` ends in non-separator
+                            } else {
+                                return Err(PatternError {
+                                    pos: i,
+                                    msg: ERROR_RECURSIVE_WILDCARDS,
+                                });
+                            }
+                            // `**` begins with non-separator
+                        } else {
+                            return Err(PatternError {
+                                pos: old - 1,
+                                msg: ERROR_RECURSIVE_WILDCARDS,
+                            });
+                        }
+                    } else {
+                        tokens.push(AnySequence);
+                    }
+                }
+                '[' => {
+                    let bstart = if i + 1 < end && self.chars[i + 1] == '!' {
+                        i + 2
+                    } else {
+                        i + 1
+                    };
+                    if let Some((cs, bend)) = parse_char_specifiers(&self.chars[..end], bstart)? {
+                        if bstart == i + 2 {
+                            tokens.push(AnyExcept(cs));
+                        } else if let [SingleChar(c)] = cs[..] {
+                            if "?*[](){},|".contains(c) {
+                                // an escaped metacharacter
+                                tokens.push(Char(c));
+                            } else {
+                                tokens.push(AnyWithin(cs));
+                            }
+                        } else {
+                            tokens.push(AnyWithin(cs));
+                        }
+                        i = bend + 1;
+                        continue;
+                    }
+
+                    // if we get here then this is not a valid range pattern
+                    return Err(PatternError {
+                        pos: i,
+                        msg: ERROR_INVALID_RANGE,
+                    });
+                }
+                '(' => {
+                    let (name, next) = match group_name(self.chars, i + 1, end) {
+                        Some((name, next)) => (Some(name), next),
+                        None => (None, i + 1),
+                    };
+                    if !self.skip_groups {
+                        if let Some(ref name) = name {
+                            if self.names.iter().any(|n| n.as_ref() == Some(name)) {
+                                return Err(PatternError {
+                                    pos: i,
+                                    msg: "Duplicate group name",
+                                });
+                            }
+                        }
+                        let n = self.new_group(name);
+                        captures_stack.push((n, i));
+                        tokens.push(StartCapture(n, false));
+                    }
+                    i = next;
+                }
+                ')' => {
+                    if !self.skip_groups {
+                        if let Some((c, _)) = captures_stack.pop() {
+                            tokens.push(EndCapture(c, false));
+                        } else {
+                            return Err(PatternError {
+                                pos: i,
+                                msg: "Unmatched closing paren",
+                            });
+                        }
+                    }
+                    i += 1;
+                }
+                '{' if self.braces => {
+                    if let Some((token, next)) = parse_num_range(&self.chars[..end], i) {
+                        tokens.push(token);
+                        i = next;
+                    } else if let Some(next) = self.parse_braces(i, end, &mut tokens)? {
+                        i = next;
+                    } else {
+                        tokens.push(Char('{'));
+                        i += 1;
+                    }
+                }
+                c if nested && path::is_separator(c) => {
+                    return Err(PatternError {
+                        pos: i,
+                        msg: ERROR_NESTED_SEPARATOR,
+                    });
+                }
+                c => {
+                    tokens.push(Char(c));
+                    i += 1;
+                }
+            }
+        }
+
+        if let Some(&(_, i)) = captures_stack.first() {
+            return Err(PatternError {
+                pos: i,
+                msg: "Unmatched opening paren",
+            })
+        }
+
+        Ok(tokens)
+    }
+
+    // Parses the extended glob operator at `i` and returns the position
+    // right after its closing paren
+    fn parse_extglob(&mut self, kind: ExtGlobKind, i: usize, end: usize,
+                     tokens: &mut Vec<PatternToken>)
+        -> Result<usize, PatternError>
     {
-        // FIXME (#9639): This needs to handle non-utf8 paths
-        path.to_str().map_or(None, |s| self.captures_with(s, options))
+        let bounds = match self.split_alternatives(i + 2, end, ')', '|')? {
+            Some(bounds) => bounds,
+            None => {
+                return Err(PatternError {
+                    pos: i + 1,
+                    msg: "Unmatched opening paren",
+                });
+            }
+        };
+
+        let capture = if self.skip_groups {
+            None
+        } else {
+            Some(self.new_group(None))
+        };
+        let alternatives = self.parse_alternatives(&bounds)?;
+        tokens.push(PatternToken::ExtGlob(kind, alternatives, capture));
+        Ok(bounds[bounds.len() - 1])
     }
 
-    /// Return entry if filename matches pattern
-    pub fn captures_with(&self, str: &str, options: &MatchOptions)
-        -> Option<Entry>
+    // Parses brace alternatives like `{a,b}` at `i` and returns the position
+    // right after the closing brace, or `None` if the braces are unclosed or
+    // have a single alternative
+    fn parse_braces(&mut self, i: usize, end: usize,
+                    tokens: &mut Vec<PatternToken>)
+        -> Result<Option<usize>, PatternError>
     {
-        use self::CaptureResult::Match;
-        let mut buf = Vec::new();
-        let iter = str.chars();
-        match self.captures_from(true, iter, 0, str, &mut buf, options) {
-            Match(()) => {
-                Some(Entry::with_captures(str, buf))
-            }
-            _ => None,
+        let bounds = match self.split_alternatives(i + 1, end, '}', ',')? {
+            Some(bounds) => bounds,
+            None => return Ok(None),
+        };
+        if bounds.len() < 3 {
+            return Ok(None);
         }
+        // braces match exactly one of the alternatives but don't capture
+        let alternatives = self.parse_alternatives(&bounds)?;
+        tokens.push(PatternToken::ExtGlob(ExtGlobKind::ExactlyOne,
+                                          alternatives, None));
+        Ok(Some(bounds[bounds.len() - 1]))
     }
 
-    fn matches_from(&self,
-                    mut follows_separator: bool,
-                    mut file: std::str::Chars,
-                    i: usize,
-                    options: &MatchOptions)
-                    -> MatchResult
+    // Finds the top-level `delimiter`s and the `close` character starting at
+    // `start`, returning the start of every alternative followed by the
+    // position right after `close`, or `None` if it's never closed
+    fn split_alternatives(&self, start: usize, end: usize,
+                          close: char, delimiter: char)
+        -> Result<Option<Vec<usize>>, PatternError>
     {
-        use self::PatternToken::*;
-
-        for (ti, token) in self.tokens[i..].iter().enumerate() {
-            match *token {
-                AnySequence | AnyRecursiveSequence => {
-                    // ** must be at the start.
-                    debug_assert!(match *token {
-                        AnyRecursiveSequence => follows_separator,
-                        _ => true,
-                    });
-
-                    // Empty match
-                    match self.matches_from(follows_separator, file.clone(), i + ti + 1, options) {
-                        SubPatternDoesntMatch => (), // keep trying
-                        m => return m,
+        let mut bounds = vec![start];
+        let mut depth = 0;
+        let mut j = start;
+        while j < end {
+            match self.chars[j] {
+                '[' => {
+                    let bstart = if j + 1 < end && self.chars[j + 1] == '!' {
+                        j + 2
+                    } else {
+                        j + 1
                     };
-
-                    while let Some(c) = file.next() {
-                        if follows_separator && options.require_literal_leading_dot && c == '.' {
-                            return SubPatternDoesntMatch;
-                        }
-                        follows_separator = path::is_separator(c);
-                        match *token {
-                            AnyRecursiveSequence if !follows_separator => continue,
-                            AnySequence if options.require_literal_separator &&
-                                           follows_separator => return SubPatternDoesntMatch,
-                            _ => (),
-                        }
-                        match self.matches_from(follows_separator,
-                                                file.clone(),
-                                                i + ti + 1,
-                                                options) {
-                            SubPatternDoesntMatch => (), // keep trying
-                            m => return m,
-                        }
+                    // parens and delimiters within brackets are just
+                    // characters
+                    if let Some((_, bend)) = parse_char_specifiers(&self.chars[..end], bstart)? {
+                        j = bend + 1;
+                        continue;
                     }
                 }
-                StartCapture(..) | EndCapture(..) => {}
-                _ => {
-                    let c = match file.next() {
-                        Some(c) => c,
-                        None => return EntirePatternDoesntMatch,
-                    };
-
-                    let is_sep = path::is_separator(c);
-
-                    if !match *token {
-                        AnyChar | AnyWithin(..) | AnyExcept(..)
-                            if (options.require_literal_separator && is_sep) ||
-                            (follows_separator && options.require_literal_leading_dot &&
-                             c == '.') => false,
-                        AnyChar => true,
-                        AnyWithin(ref specifiers) => in_char_specifiers(&specifiers, c, options),
-                        AnyExcept(ref specifiers) => !in_char_specifiers(&specifiers, c, options),
-                        Char(c2) => chars_eq(c, c2, options.case_sensitive),
-                        AnySequence | AnyRecursiveSequence => unreachable!(),
-                        StartCapture(..) | EndCapture(..) => unreachable!(),
-                    } {
-                        return SubPatternDoesntMatch;
-                    }
-                    follows_separator = is_sep;
+                c if c == close && depth == 0 => {
+                    bounds.push(j + 1);
+                    return Ok(Some(bounds));
                 }
+                '(' => depth += 1,
+                '{' if self.braces => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                '}' if depth > 0 && self.braces => depth -= 1,
+                c if c == delimiter && depth == 0 => bounds.push(j + 1),
+                _ => {}
             }
+            j += 1;
         }
+        Ok(None)
+    }
 
-        // Iter is fused.
-        if file.next().is_none() {
-            Match
-        } else {
-            SubPatternDoesntMatch
-        }
+    // Allocates the number for the next capture group
+    fn new_group(&mut self, name: Option<String>) -> usize {
+        self.names.push(name);
+        self.names.len() - 1
     }
 
-    fn captures_from(&self,
-                    mut follows_separator: bool,
-                    mut file: std::str::Chars,
-                    i: usize, fname: &str,
-                    captures: &mut Vec<(usize, usize)>,
-                    options: &MatchOptions)
-        -> CaptureResult
+    fn parse_alternatives(&mut self, bounds: &[usize])
+        -> Result<Vec<Vec<PatternToken>>, PatternError>
     {
-        use self::PatternToken::*;
-        use self::CaptureResult::*;
+        let mut alternatives = Vec::with_capacity(bounds.len() - 1);
+        for pair in bounds.windows(2) {
+            alternatives.push(self.parse(pair[0], pair[1] - 1, true)?);
+        }
+        Ok(alternatives)
+    }
+}
 
-        for (ti, token) in self.tokens[i..].iter().enumerate() {
-            match *token {
-                AnySequence | AnyRecursiveSequence => {
-                    // ** must be at the start.
-                    debug_assert!(match *token {
-                        AnyRecursiveSequence => follows_separator,
-                        _ => true,
-                    });
+// Offset of `rest` (which must be a slice of `whole`) from the start of
+// `whole`
+fn offset_in(whole: &str, rest: &str) -> usize {
+    rest.as_ptr() as usize - whole.as_ptr() as usize
+}
 
-                    // Empty match
-                    match self.captures_from(follows_separator, file.clone(),
-                        i + ti + 1, fname, captures, options)
-                    {
-                        SubPatternDoesntMatch => (), // keep trying
-                        m => return m,
-                    };
+// Possible lengths of the text matched by an extended glob operator,
+// shortest first. Operators never match path separators.
+fn extglob_ends(text: &str, options: &MatchOptions) -> Vec<usize> {
+    let limit = text.find(|c| options.separator.is_separator(c))
+        .unwrap_or(text.len());
+    text[..limit].char_indices().map(|(i, _)| i)
+        .chain(Some(limit))
+        .collect()
+}
 
-                    while let Some(c) = file.next() {
-                        if follows_separator && options.require_literal_leading_dot && c == '.' {
-                            return SubPatternDoesntMatch;
-                        }
-                        follows_separator = path::is_separator(c);
-                        match *token {
-                            AnyRecursiveSequence if !follows_separator => continue,
-                            AnySequence if options.require_literal_separator &&
-                                           follows_separator => return SubPatternDoesntMatch,
-                            _ => (),
-                        }
-                        match self.captures_from(follows_separator,
-                                                file.clone(),
-                                                i + ti + 1,
-                                                fname, captures,
-                                                options) {
-                            SubPatternDoesntMatch => (), // keep trying
-                            m => return m,
-                        }
-                    }
-                }
-                StartCapture(n, flag) => {
-                    let mut off = fname.len() - file.as_str().len();
-                    if flag && fname[..off].ends_with('/') {
-                        off -= 1;
-                    }
-                    while captures.len() < n+1 {
-                        captures.push((0, 0));
-                    }
-                    captures[n] = (off, off);
-                }
-                EndCapture(n, flag) => {
-                    let mut off = fname.len() - file.as_str().len();
-                    if flag && fname[..off].ends_with('/') {
-                        off -= 1;
-                    }
-                    if off < captures[n].0 {
-                        // if "a/**/b" matches "a/b"
-                        off = captures[n].0;
-                    }
-                    captures[n].1 = off;
-                }
-                _ => {
-                    let c = match file.next() {
-                        Some(pair) => pair,
-                        None => return EntirePatternDoesntMatch,
-                    };
+fn alternative_matches(tokens: &[PatternToken], follows_separator: bool,
+                       text: &str, options: &MatchOptions)
+    -> bool
+{
+    Pattern::matches_from(tokens, follows_separator, text.chars(), options)
+        == Match
+}
 
-                    let is_sep = path::is_separator(c);
+// Checks if the whole `piece` is matched by the extended glob operator
+fn match_extglob(kind: ExtGlobKind, alternatives: &[Vec<PatternToken>],
+                 follows_separator: bool, piece: &str, options: &MatchOptions)
+    -> bool
+{
+    use self::ExtGlobKind::*;
+
+    let any = |text: &str| alternatives.iter()
+        .any(|alt| alternative_matches(alt, follows_separator, text, options));
+    match kind {
+        ExactlyOne => any(piece),
+        ZeroOrOne => piece.is_empty() || any(piece),
+        ZeroOrMore => {
+            piece.is_empty() ||
+            last_repetition(alternatives, follows_separator, piece, options)
+                .is_some()
+        }
+        OneOrMore => {
+            last_repetition(alternatives, follows_separator, piece, options)
+                .is_some()
+        }
+        Not => {
+            // like `*`, negation doesn't match hidden files
+            if follows_separator && options.require_literal_leading_dot &&
+                piece.starts_with('.')
+            {
+                return false;
+            }
+            !any(piece)
+        }
+    }
+}
 
-                    if !match *token {
-                        AnyChar | AnyWithin(..) | AnyExcept(..)
-                            if (options.require_literal_separator && is_sep) ||
-                            (follows_separator && options.require_literal_leading_dot &&
-                             c == '.') => false,
-                        AnyChar => true,
-                        AnyWithin(ref specifiers) => in_char_specifiers(&specifiers, c, options),
-                        AnyExcept(ref specifiers) => !in_char_specifiers(&specifiers, c, options),
-                        Char(c2) => chars_eq(c, c2, options.case_sensitive),
-                        AnySequence | AnyRecursiveSequence => unreachable!(),
-                        StartCapture(..) | EndCapture(..) => unreachable!(),
-                    } {
-                        return SubPatternDoesntMatch;
-                    }
-                    follows_separator = is_sep;
-                }
+// Splits `piece` into one or more repetitions of the alternatives, returning
+// the index of the alternative and the offset of the last repetition
+fn last_repetition(alternatives: &[Vec<PatternToken>],
+                   follows_separator: bool, piece: &str,
+                   options: &MatchOptions)
+    -> Option<(usize, usize)>
+{
+    if piece.is_empty() {
+        return alternatives.iter()
+            .position(|alt| alternative_matches(alt, follows_separator,
+                                                "", options))
+            .map(|idx| (idx, 0));
+    }
+    let bounds = piece.char_indices().map(|(i, _)| i)
+        .chain(Some(piece.len()))
+        .collect::<Vec<_>>();
+    // for every boundary: how the repetition ending there was matched
+    let mut reached = vec![None; bounds.len()];
+    reached[0] = Some((0, 0));
+    for (si, &start) in bounds.iter().enumerate() {
+        if reached[si].is_none() {
+            continue;
+        }
+        let follows_separator = follows_separator && start == 0;
+        for (ei, &end) in bounds.iter().enumerate().skip(si + 1) {
+            if reached[ei].is_some() {
+                continue;
             }
+            let text = &piece[start..end];
+            reached[ei] = alternatives.iter()
+                .position(|alt| alternative_matches(alt, follows_separator,
+                                                    text, options))
+                .map(|idx| (idx, start));
         }
+    }
+    reached[bounds.len() - 1]
+}
 
-        // Iter is fused.
-        if file.next().is_none() {
-            Match(())
-        } else {
-            SubPatternDoesntMatch
+// Records the capture groups nested in the alternatives of the extended glob
+// operator that is known to match `piece`
+fn fill_extglob_captures(kind: ExtGlobKind,
+                         alternatives: &[Vec<PatternToken>],
+                         follows_separator: bool, piece: &str, fname: &str,
+                         captures: &mut Vec<(usize, usize)>,
+                         options: &MatchOptions)
+{
+    use self::ExtGlobKind::*;
+
+    if alternatives.iter().all(|alt| count_groups(alt) == 0) {
+        return;
+    }
+    // reset the groups left from the previous tries
+    for alt in alternatives {
+        reset_groups(alt, captures);
+    }
+    let matched = match kind {
+        ExactlyOne | ZeroOrOne => {
+            alternatives.iter()
+                .position(|alt| alternative_matches(alt, follows_separator,
+                                                    piece, options))
+                .map(|idx| (idx, 0))
         }
+        ZeroOrMore | OneOrMore => {
+            last_repetition(alternatives, follows_separator, piece, options)
+        }
+        Not => None,
+    };
+    if let Some((idx, start)) = matched {
+        Pattern::captures_from(&alternatives[idx],
+                               follows_separator && start == 0,
+                               piece[start..].chars(), fname, captures,
+                               options);
     }
-    /// Substitute values back into patterns replacing capture groups
-    ///
-    /// ```rust
-    /// # use std::error::Error;
-    /// use capturing_glob::Pattern;
-    ///
-    /// # fn run() -> Result<(), Box<Error>> {
-    /// assert_eq!(Pattern::new("images/(*).jpg")?.substitute(&["cat"])?,
-    ///            "images/cat.jpg");
-    /// # Ok(())
-    /// # }
-    /// # fn main() { run().unwrap() }
-    /// ```
-    ///
-    /// Note: we check neither result so it matches pattern.
-    pub fn substitute(&self, capture_groups: &[&str])
-        -> Result<String, SubstitutionError>
-    {
-        use self::PatternToken::*;
+}
 
-        let mut result = String::with_capacity(self.original.len());
-        let mut iter = self.tokens.iter();
-        while let Some(tok) = iter.next() {
-            match *tok {
-                Char(c) => result.push(c),
-                AnyChar | AnySequence | AnyRecursiveSequence |
-                AnyWithin(..) | AnyExcept(..)
-                => {
-                    return Err(SubstitutionError::UnexpectedWildcard);
-                }
-                StartCapture(idx, _) => {
-                    if let Some(val) = capture_groups.get(idx) {
-                        result.push_str(val);
-                    } else {
-                        return Err(SubstitutionError::MissingGroup(idx));
-                    }
-                    for tok in iter.by_ref() {
-                        match *tok {
-                            EndCapture(i, _) if idx == i => break,
-                            _ => {}
-                        }
-                    }
-                }
-                EndCapture(_, _) => unreachable!(),
+// Returns the number of capture groups in the tokens
+fn count_groups(tokens: &[PatternToken]) -> usize {
+    use self::PatternToken::*;
+
+    tokens.iter().map(|token| match *token {
+        StartCapture(n, _) => n + 1,
+        ExtGlob(_, ref alternatives, capture) => {
+            alternatives.iter().map(|alt| count_groups(alt))
+                .chain(capture.map(|n| n + 1))
+                .max().unwrap_or(0)
+        }
+        _ => 0,
+    }).max().unwrap_or(0)
+}
+
+fn reset_groups(tokens: &[PatternToken], captures: &mut [(usize, usize)]) {
+    use self::PatternToken::*;
+
+    for token in tokens {
+        match *token {
+            StartCapture(n, _) | ExtGlob(_, _, Some(n)) if n < captures.len() => {
+                captures[n] = (0, 0);
+            }
+            _ => {}
+        }
+        if let ExtGlob(_, ref alternatives, _) = *token {
+            for alt in alternatives {
+                reset_groups(alt, captures);
             }
         }
-        return Ok(result)
     }
 }
 
 // Fills `todo` with paths under `path` to be matched by `patterns[idx]`,
 // special-casing patterns to match `.` and `..`, and avoiding `readdir()`
-// calls when there are no metacharacters in the pattern.
-fn fill_todo(todo: &mut Vec<Result<(PathBuf, usize), GlobError>>,
+// calls when there are no metacharacters in the pattern. The `path` is
+// `seen` if it was found in a directory listing or checked to exist during
+// the walk, i.e. it's not the directory the walk starts from.
+#[allow(clippy::too_many_arguments)]
+fn fill_todo<F: FileSystem>(fs: &F,
+             progress: &mut Progress,
+             todo: &mut Vec<Result<(PathBuf, usize), GlobError>>,
              patterns: &[Pattern],
              idx: usize,
              path: &Path,
+             seen: bool,
              options: &MatchOptions) {
     // convert a pattern that's just many Char(_) to a string
     fn pattern_as_str(pattern: &Pattern) -> Option<String> {
@@ -1166,24 +2636,29 @@ fn fill_todo(todo: &mut Vec<Result<(PathBuf, usize), GlobError>>,
                 _ => return None,
             }
         }
-        return Some(s);
+        Some(s)
     }
 
-    let add = |todo: &mut Vec<_>, next_path: PathBuf| {
+    let add = |progress: &mut Progress, todo: &mut Vec<_>, next_path: PathBuf| {
         if idx + 1 == patterns.len() {
             // We know it's good, so don't make the iterator match this path
             // against the pattern again. In particular, it can't match
             // . or .. globs since these never show up as path components.
-            todo.push(Ok((next_path, !0 as usize)));
+            todo.push(Ok((next_path, !0)));
         } else {
-            fill_todo(todo, patterns, idx + 1, &next_path, options);
+            fill_todo(fs, progress, todo, patterns, idx + 1, &next_path,
+                      true, options);
         }
     };
 
     let pattern = &patterns[idx];
-    let is_dir = is_dir(path);
+    let is_dir = fs.is_dir(path);
     let curdir = path == Path::new(".");
-    match pattern_as_str(pattern) {
+    // with normalization the file might be named differently from the
+    // literal, so we have to read the directory
+    let literal = pattern_as_str(pattern)
+        .filter(|s| s.is_ascii() || !options.normalization_insensitive);
+    match literal {
         Some(s) => {
             // This pattern component doesn't have any metacharacters, so we
             // don't need to read the current directory to know where to
@@ -1196,68 +2671,223 @@ fn fill_todo(todo: &mut Vec<Result<(PathBuf, usize), GlobError>>,
             } else {
                 path.join(&s)
             };
-            if (special && is_dir) || (!special && fs::metadata(&next_path).is_ok()) {
-                add(todo, next_path);
+            if (special && is_dir) || (!special && fs.exists(&next_path)) {
+                add(progress, todo, next_path);
             }
         }
         None if is_dir => {
-            let dirs = fs::read_dir(path).and_then(|d| {
-                d.map(|e| {
-                     e.map(|e| {
-                         if curdir {
-                             PathBuf::from(e.path().file_name().unwrap())
-                         } else {
-                             e.path()
-                         }
-                     })
-                 })
-                 .collect::<Result<Vec<_>, _>>()
-            });
-            match dirs {
-                Ok(mut children) => {
+            progress.dirs_read += 1;
+            match fs.read_dir(path) {
+                Ok(names) => {
+                    let mut children = Vec::with_capacity(names.len());
+                    let mut errors = Vec::new();
+                    for name in names {
+                        match name {
+                            Ok(name) if curdir => {
+                                children.push(PathBuf::from(name));
+                            }
+                            Ok(name) => children.push(path.join(name)),
+                            // the rest of the directory is still usable
+                            Err(e) => errors.push(GlobError::new(
+                                path.to_path_buf(), e, idx, pattern, seen)),
+                        }
+                    }
                     children.sort_by(|p1, p2| p2.file_name().cmp(&p1.file_name()));
                     todo.extend(children.into_iter().map(|x| Ok((x, idx))));
+                    todo.extend(errors.into_iter().map(Err));
 
                     // Matching the special directory entries . and .. that
                     // refer to the current and parent directory respectively
                     // requires that the pattern has a leading dot, even if the
                     // `MatchOptions` field `require_literal_leading_dot` is not
                     // set.
-                    if pattern.tokens.len() > 0 && pattern.tokens[0] == PatternToken::Char('.') {
+                    if pattern.tokens.first() == Some(&PatternToken::Char('.')) {
                         for &special in [".", ".."].iter() {
                             if pattern.matches_with(special, options) {
-                                add(todo, path.join(special));
+                                add(progress, todo, path.join(special));
                             }
                         }
                     }
                 }
-                Err(e) => {
-                    todo.push(Err(GlobError {
-                        path: path.to_path_buf(),
-                        error: e,
-                    }));
+                Err(e) => {
+                    todo.push(Err(GlobError::new(path.to_path_buf(), e, idx,
+                                                 pattern, seen)));
+                }
+            }
+        }
+        None => {
+            // not a directory, nothing more to find
+        }
+    }
+}
+
+// Parses a numeric range like `{1..20}` or `{00..63}` starting at `start`,
+// returning the token along with the index right after the closing brace, or
+// `None` if this is not a well-formed range.
+fn parse_num_range(chars: &[char], start: usize)
+    -> Option<(PatternToken, usize)>
+{
+    let close = start + chars[start..].iter().position(|&c| c == '}')?;
+    let body = chars[start + 1..close].iter().collect::<String>();
+    let (first, last) = body.split_once("..")?;
+    let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_number(first) || !is_number(last) {
+        return None;
+    }
+    // as in shells, a leading zero on either side pads all the numbers
+    let padded = |s: &str| s.len() > 1 && s.starts_with('0');
+    let width = if padded(first) || padded(last) {
+        cmp::max(first.len(), last.len())
+    } else {
+        0
+    };
+    let token = PatternToken::NumRange(first.parse().ok()?, last.parse().ok()?,
+                                       width);
+    Some((token, close + 1))
+}
+
+// Possible lengths of the number at the start of `text` that is within the
+// range, shortest first
+fn num_range_ends(text: &str, first: u64, last: u64, width: usize)
+    -> Vec<usize>
+{
+    let (low, high) = (cmp::min(first, last), cmp::max(first, last));
+    let digits = text.bytes().take_while(|b| b.is_ascii_digit()).count();
+    let max_len = if width > 0 { width } else { high.to_string().len() };
+    (1..cmp::min(digits, max_len) + 1).filter(|&len| {
+        let num = &text[..len];
+        if width > 0 && len != width {
+            return false;
+        }
+        if width == 0 && len > 1 && num.starts_with('0') {
+            return false;
+        }
+        num.parse::<u64>().is_ok_and(|n| low <= n && n <= high)
+    }).collect()
+}
+
+// Returns all the strings matched by the tokens, if there are finitely many
+// and no more than `limit`
+fn expand_tokens(tokens: &[PatternToken], limit: usize)
+    -> Result<Vec<String>, SubstitutionError>
+{
+    use self::PatternToken::*;
+    use self::ExtGlobKind::*;
+
+    let mut result = vec![String::new()];
+    for token in tokens {
+        let suffixes = match *token {
+            Char(c) => {
+                for item in &mut result {
+                    item.push(c);
+                }
+                continue;
+            }
+            NumRange(first, last, width) => {
+                let (low, high) = (cmp::min(first, last), cmp::max(first, last));
+                if high - low >= limit as u64 {
+                    return Err(SubstitutionError::TooManyPaths(limit));
+                }
+                let numbers = (low..=high)
+                    .map(|n| format!("{:01$}", n, width));
+                if first <= last {
+                    numbers.collect::<Vec<_>>()
+                } else {
+                    numbers.rev().collect()
+                }
+            }
+            ExtGlob(ExactlyOne, ref alternatives, _) |
+            ExtGlob(ZeroOrOne, ref alternatives, _) => {
+                let mut suffixes = Vec::new();
+                if let ExtGlob(ZeroOrOne, ..) = *token {
+                    suffixes.push(String::new());
+                }
+                for alt in alternatives {
+                    suffixes.extend(expand_tokens(alt, limit)?);
+                    if suffixes.len() > limit {
+                        return Err(SubstitutionError::TooManyPaths(limit));
+                    }
                 }
+                suffixes
             }
+            StartCapture(..) | EndCapture(..) => continue,
+            AnyChar | AnySequence | AnyRecursiveSequence |
+            AnyWithin(..) | AnyExcept(..) | ExtGlob(..) => {
+                return Err(SubstitutionError::UnexpectedWildcard);
+            }
+        };
+        if result.len().saturating_mul(suffixes.len()) > limit {
+            return Err(SubstitutionError::TooManyPaths(limit));
         }
-        None => {
-            // not a directory, nothing more to find
-        }
+        result = result.iter().flat_map(|prefix| {
+            suffixes.iter().map(move |suffix| format!("{}{}", prefix, suffix))
+        }).collect();
     }
+    Ok(result)
 }
 
-fn parse_char_specifiers(s: &[char]) -> Vec<CharSpecifier> {
+// Parses the contents of a bracket expression starting at `start` (i.e. just
+// after `[` or `[!`), returning the specifiers along with the index of the
+// closing `]`, or `None` if the bracket is never closed.
+fn parse_char_specifiers(chars: &[char], start: usize)
+    -> Result<Option<(Vec<CharSpecifier>, usize)>, PatternError>
+{
+    enum Item {
+        Char(char),
+        Class(CharClass),
+    }
+
+    let mut items = Vec::new();
+    let mut i = start;
+    let end = loop {
+        if i >= chars.len() {
+            return Ok(None);
+        }
+        if chars[i] == '[' && chars.get(i + 1) == Some(&':') {
+            let close = chars[i + 2..].windows(2)
+                .position(|w| w[0] == ':' && w[1] == ']');
+            if let Some(len) = close {
+                let name = chars[i + 2..i + 2 + len].iter().collect::<String>();
+                match CharClass::from_name(&name) {
+                    Some(class) => items.push(Item::Class(class)),
+                    None => {
+                        return Err(PatternError {
+                            pos: i,
+                            msg: ERROR_INVALID_CLASS,
+                        });
+                    }
+                }
+                i += len + 4;
+                continue;
+            }
+        }
+        // a `]` right after the opening bracket is a part of the set
+        if chars[i] == ']' && i > start {
+            break i;
+        }
+        items.push(Item::Char(chars[i]));
+        i += 1;
+    };
+
     let mut cs = Vec::new();
     let mut i = 0;
-    while i < s.len() {
-        if i + 3 <= s.len() && s[i + 1] == '-' {
-            cs.push(CharRange(s[i], s[i + 2]));
-            i += 3;
-        } else {
-            cs.push(SingleChar(s[i]));
-            i += 1;
+    while i < items.len() {
+        match (&items[i], items.get(i + 1), items.get(i + 2)) {
+            (&Item::Char(a), Some(&Item::Char('-')), Some(&Item::Char(b))) => {
+                cs.push(CharRange(a, b));
+                i += 3;
+            }
+            (&Item::Char(c), _, _) => {
+                cs.push(SingleChar(c));
+                i += 1;
+            }
+            (&Item::Class(class), _, _) => {
+                cs.push(Class(class));
+                i += 1;
+            }
         }
     }
-    cs
+    Ok(Some((cs, end)))
 }
 
 fn in_char_specifiers(specifiers: &[CharSpecifier], c: char, options: &MatchOptions) -> bool {
@@ -1265,32 +2895,31 @@ fn in_char_specifiers(specifiers: &[CharSpecifier], c: char, options: &MatchOpti
     for &specifier in specifiers.iter() {
         match specifier {
             SingleChar(sc) => {
-                if chars_eq(c, sc, options.case_sensitive) {
+                if chars_eq(c, sc, options) {
                     return true;
                 }
             }
             CharRange(start, end) => {
 
-                // FIXME: work with non-ascii chars properly (issue #1347)
-                if !options.case_sensitive && c.is_ascii() && start.is_ascii() && end.is_ascii() {
-
-                    let start = start.to_ascii_lowercase();
-                    let end = end.to_ascii_lowercase();
-
-                    let start_up = start.to_uppercase().next().unwrap();
-                    let end_up = end.to_uppercase().next().unwrap();
+                if c >= start && c <= end {
+                    return true;
+                }
 
-                    // only allow case insensitive matching when
-                    // both start and end are within a-z or A-Z
-                    if start != start_up && end != end_up {
-                        let c = c.to_ascii_lowercase();
-                        if c >= start && c <= end {
-                            return true;
-                        }
+                // only allow case insensitive matching when both start and
+                // end are letters that have case, e.g. `a-z` or `А-Я`
+                if !options.case_sensitive && is_cased(start) && is_cased(end) {
+                    let variants = [
+                        fold_case(c),
+                        single_char(c.to_lowercase()).unwrap_or(c),
+                        single_char(c.to_uppercase()).unwrap_or(c),
+                    ];
+                    if variants.iter().any(|&v| v >= start && v <= end) {
+                        return true;
                     }
                 }
-
-                if c >= start && c <= end {
+            }
+            Class(class) => {
+                if class.matches(c, options) {
                     return true;
                 }
             }
@@ -1300,27 +2929,146 @@ fn in_char_specifiers(specifiers: &[CharSpecifier], c: char, options: &MatchOpti
     false
 }
 
-/// A helper function to determine if two chars are (possibly case-insensitively) equal.
-fn chars_eq(a: char, b: char, case_sensitive: bool) -> bool {
-    if cfg!(windows) && path::is_separator(a) && path::is_separator(b) {
-        true
+impl CharClass {
+    fn from_name(name: &str) -> Option<CharClass> {
+        use self::CharClass::*;
+        Some(match name {
+            "alnum" => Alnum,
+            "alpha" => Alpha,
+            "blank" => Blank,
+            "cntrl" => Cntrl,
+            "digit" => Digit,
+            "graph" => Graph,
+            "lower" => Lower,
+            "print" => Print,
+            "punct" => Punct,
+            "space" => Space,
+            "upper" => Upper,
+            "xdigit" => Xdigit,
+            _ => return None,
+        })
+    }
+
+    fn name(self) -> &'static str {
+        use self::CharClass::*;
+        match self {
+            Alnum => "alnum",
+            Alpha => "alpha",
+            Blank => "blank",
+            Cntrl => "cntrl",
+            Digit => "digit",
+            Graph => "graph",
+            Lower => "lower",
+            Print => "print",
+            Punct => "punct",
+            Space => "space",
+            Upper => "upper",
+            Xdigit => "xdigit",
+        }
+    }
+
+    fn matches(self, c: char, options: &MatchOptions) -> bool {
+        use self::CharClass::*;
+        if !options.unicode_classes && !c.is_ascii() {
+            return false;
+        }
+        match self {
+            // like in `fnmatch` with `FNM_CASEFOLD`, case-insensitive
+            // matching makes `upper` and `lower` match any cased letter
+            Upper | Lower if !options.case_sensitive => {
+                c.is_uppercase() || c.is_lowercase()
+            }
+            Upper => c.is_uppercase(),
+            Lower => c.is_lowercase(),
+            // `digit` is decimal digits only, so numerals like `½` or `Ⅻ`
+            // are neither digits nor alphanumeric
+            Alnum => c.is_alphabetic() || c.is_ascii_digit(),
+            Alpha => c.is_alphabetic(),
+            Digit => c.is_ascii_digit(),
+            Xdigit => c.is_ascii_hexdigit(),
+            Space => c.is_whitespace(),
+            Blank => c == '\t' || (c.is_whitespace() && !is_line_break(c)),
+            Cntrl => c.is_control(),
+            Graph => !c.is_whitespace() && !c.is_control(),
+            Print => c == ' ' || (!c.is_whitespace() && !c.is_control()),
+            Punct => {
+                !c.is_whitespace() && !c.is_control() &&
+                    !c.is_alphabetic() && !c.is_ascii_digit()
+            }
+        }
+    }
+}
+
+fn is_line_break(c: char) -> bool {
+    matches!(c, '\n' | '\x0b' | '\x0c' | '\r' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+// Length of the separator the string ends with, if any
+fn trailing_separator(s: &str, options: &MatchOptions) -> usize {
+    match s.chars().next_back() {
+        Some(c) if options.separator.is_separator(c) => c.len_utf8(),
+        _ => 0,
+    }
+}
+
+/// A helper function to determine if a char of the string is (possibly
+/// case-insensitively) equal to the char of the pattern.
+fn chars_eq(a: char, b: char, options: &MatchOptions) -> bool {
+    let case_sensitive = options.case_sensitive;
+    if b == '/' || options.separator.is_separator(b) {
+        // a separator in the pattern matches any separator
+        options.separator.is_separator(a)
     } else if !case_sensitive && a.is_ascii() && b.is_ascii() {
-        // FIXME: work with non-ascii chars properly (issue #9084)
-        a.to_ascii_lowercase() == b.to_ascii_lowercase()
+        a.eq_ignore_ascii_case(&b)
+    } else if !case_sensitive {
+        a == b || fold_case(a) == fold_case(b)
     } else {
         a == b
     }
 }
 
+/// Unicode simple case folding of a single character.
+///
+/// This is a one-to-one mapping (so `ß` doesn't match `ss`), which keeps
+/// every matched character at the same place in the original string. The
+/// mapping is derived from the standard library's case conversions: a
+/// character is folded to the lowercase of its uppercase form, so that
+/// e.g. `ς`, `σ` and `Σ` are all the same letter.
+fn fold_case(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+    // dotless i is only equal to `I` in Turkic locales, and simple case
+    // folding keeps it as is
+    if c == '\u{131}' {
+        return c;
+    }
+    let upper = single_char(c.to_uppercase()).unwrap_or(c);
+    single_char(upper.to_lowercase()).unwrap_or(c)
+}
+
+fn single_char<I: Iterator<Item=char>>(mut iter: I) -> Option<char> {
+    match (iter.next(), iter.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+fn is_cased(c: char) -> bool {
+    c.is_lowercase() || c.is_uppercase()
+}
+
 
 /// Configuration options to modify the behaviour of `Pattern::matches_with(..)`.
 #[allow(missing_copy_implementations)]
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default = "MatchOptions::new"))]
 pub struct MatchOptions {
     /// Whether or not patterns should be matched in a case-sensitive manner.
-    /// This currently only considers upper/lower case relationships between
-    /// ASCII characters, but in future this might be extended to work with
-    /// Unicode.
+    /// Case-insensitive matching uses Unicode simple case folding, so `Ä`
+    /// matches `ä` and `Σ` matches both `σ` and `ς`, but multi-character
+    /// foldings like `ß` vs `ss` are not considered equal.
     pub case_sensitive: bool,
 
     /// Whether or not path-component separator characters (e.g. `/` on
@@ -1334,6 +3082,24 @@ pub struct MatchOptions {
     /// conventionally considered hidden on Unix systems and it might be
     /// desirable to skip them when listing files.
     pub require_literal_leading_dot: bool,
+
+    /// Whether or not strings are compared after Unicode normalization, so
+    /// that precomposed characters (NFC, typical for typed patterns) match
+    /// decomposed ones (NFD, typical for files coming from macOS), e.g.
+    /// `café` written either way. Captured groups still refer to the
+    /// original, un-normalized string. Note that with this option glob
+    /// has to read directories even for literal non-ASCII components.
+    pub normalization_insensitive: bool,
+
+    /// Whether or not POSIX character classes like `[[:alpha:]]` match
+    /// non-ASCII characters according to their Unicode properties (so that
+    /// `[[:alpha:]]` matches `ä` and `[[:space:]]` matches a no-break space).
+    /// By default classes only match ASCII characters, as in the "C" locale.
+    pub unicode_classes: bool,
+
+    /// Which characters of the matched strings separate path components,
+    /// see `Separator`.
+    pub separator: Separator,
 }
 
 impl MatchOptions {
@@ -1347,7 +3113,10 @@ impl MatchOptions {
     /// MatchOptions {
     ///     case_sensitive: true,
     ///     require_literal_separator: false,
-    ///     require_literal_leading_dot: false
+    ///     require_literal_leading_dot: false,
+    ///     normalization_insensitive: false,
+    ///     unicode_classes: false,
+    ///     separator: Separator::Platform,
     /// }
     /// ```
     pub fn new() -> MatchOptions {
@@ -1355,6 +3124,79 @@ impl MatchOptions {
             case_sensitive: true,
             require_literal_separator: false,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
+        }
+    }
+}
+
+/// Characters that separate path components in the matched strings
+///
+/// The separator affects `**`, `require_literal_separator`,
+/// `require_literal_leading_dot` and extended glob operators. A `/` in the
+/// pattern matches any separator character, so `**`, which must be
+/// delimited by `/`, works with any separator.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Separator {
+    /// The separators of the current platform, i.e. `/` on Posix, and both
+    /// `/` and `\` on Windows
+    #[default]
+    Platform,
+    /// Only `/`, regardless of the platform. Useful for URL paths,
+    /// object-store keys and archive entries.
+    Slash,
+    /// A custom character, e.g. `:` for `host:path` style names.
+    Char(char),
+}
+
+impl Separator {
+    /// Return true if the character of the matched string is a separator
+    pub fn is_separator(&self, c: char) -> bool {
+        match *self {
+            Separator::Platform => path::is_separator(c),
+            Separator::Slash => c == '/',
+            Separator::Char(sep) => c == sep,
+        }
+    }
+}
+
+
+/// Configuration options that enable optional pattern syntax, used by
+/// `Pattern::new_with(..)` and `glob_with_options(..)`.
+#[allow(missing_copy_implementations)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+pub struct PatternOptions {
+    /// Whether or not ksh-style extended glob operators are recognized:
+    /// `?(a|b)`, `*(a|b)`, `+(a|b)`, `@(a|b)` and `!(a|b)` which match zero
+    /// or one, zero or more, one or more, exactly one occurrence of the
+    /// alternatives, or anything but them respectively. Like the other
+    /// wildcards these operators never match a path separator.
+    pub extglob: bool,
+
+    /// Whether or not numeric ranges like `{1..20}` and alternatives like
+    /// `{a,b}` are recognized. When unset, braces are matched literally.
+    pub braces: bool,
+}
+
+impl PatternOptions {
+    /// Constructs a new `PatternOptions` with default field values, i.e.
+    /// the syntax accepted by `Pattern::new(..)`.
+    ///
+    /// This function always returns this value:
+    ///
+    /// ```rust,ignore
+    /// PatternOptions {
+    ///     extglob: false,
+    ///     braces: false,
+    /// }
+    /// ```
+    pub fn new() -> PatternOptions {
+        PatternOptions {
+            extglob: false,
+            braces: false,
         }
     }
 }
@@ -1362,7 +3204,16 @@ impl MatchOptions {
 #[cfg(test)]
 mod test {
     use std::path::Path;
-    use super::{glob, Pattern, MatchOptions};
+    use super::{glob, Pattern, MatchOptions, PatternOptions, CaptureSpans};
+    use super::{PatternError, Separator};
+    use super::SubstitutionError;
+
+    fn braces(pattern: &str) -> Result<Pattern, PatternError> {
+        Pattern::new_with(pattern, &PatternOptions {
+            braces: true,
+            .. PatternOptions::new()
+        })
+    }
 
     #[test]
     fn test_pattern_from_str() {
@@ -1513,7 +3364,7 @@ mod test {
     #[test]
     fn test_lots_of_files() {
         // this is a good test because it touches lots of differently named files
-        glob("/*/*/*/*").unwrap().skip(10000).next();
+        glob("/*/*/*/*").unwrap().nth(10000);
     }
 
     #[test]
@@ -1564,6 +3415,96 @@ mod test {
         assert!(!Pattern::new("[!-]").unwrap().matches("-"));
     }
 
+    #[test]
+    fn test_char_classes() {
+        let pat = Pattern::new("v[[:digit:]][[:digit:]]").unwrap();
+        assert!(pat.matches("v12"));
+        assert!(!pat.matches("v1a"));
+
+        let pat = Pattern::new("[![:alnum:]]").unwrap();
+        assert!(pat.matches("_"));
+        assert!(!pat.matches("a"));
+        assert!(!pat.matches("7"));
+
+        let pat = Pattern::new("[[:digit:]_-]").unwrap();
+        assert!(pat.matches("5"));
+        assert!(pat.matches("_"));
+        assert!(pat.matches("-"));
+        assert!(!pat.matches("x"));
+
+        let pat = Pattern::new("[a-c[:upper:]]").unwrap();
+        assert!(pat.matches("b"));
+        assert!(pat.matches("Q"));
+        assert!(!pat.matches("q"));
+
+        assert!(Pattern::new("[[:space:]]").unwrap().matches(" "));
+        assert!(Pattern::new("[[:blank:]]").unwrap().matches("\t"));
+        assert!(!Pattern::new("[[:blank:]]").unwrap().matches("\n"));
+        assert!(Pattern::new("[[:punct:]]").unwrap().matches("!"));
+        assert!(Pattern::new("[[:xdigit:]]").unwrap().matches("F"));
+        assert!(!Pattern::new("[[:xdigit:]]").unwrap().matches("g"));
+
+        // not a class, just the `[` and `:` characters
+        let pat = Pattern::new("[[:]").unwrap();
+        assert!(pat.matches("["));
+        assert!(pat.matches(":"));
+    }
+
+    #[test]
+    fn test_char_class_errors() {
+        assert_eq!(Pattern::new("a[[:foo:]]").unwrap_err().pos, 2);
+        assert_eq!(Pattern::new("a[![:alpha:]").unwrap_err().pos, 1);
+        assert_eq!(Pattern::new("[[:alpha:]").unwrap_err().pos, 0);
+    }
+
+    #[test]
+    fn test_char_classes_unicode() {
+        let unicode = MatchOptions {
+            unicode_classes: true,
+            .. MatchOptions::new()
+        };
+        let pat = Pattern::new("[[:alpha:]]").unwrap();
+        assert!(!pat.matches("ä"));
+        assert!(pat.matches_with("ä", &unicode));
+        assert!(pat.matches_with("ж", &unicode));
+
+        let pat = Pattern::new("[![:alpha:]]").unwrap();
+        assert!(pat.matches("ä"));
+        assert!(!pat.matches_with("ä", &unicode));
+
+        // digit and xdigit are always ASCII
+        assert!(!Pattern::new("[[:xdigit:]]").unwrap().matches_with("Ａ", &unicode));
+        let pat = Pattern::new("[[:digit:]]").unwrap();
+        assert!(pat.matches_with("7", &unicode));
+        assert!(!pat.matches_with("½", &unicode));
+        assert!(!pat.matches_with("Ⅻ", &unicode));
+        assert!(!Pattern::new("[[:alnum:]]").unwrap().matches_with("½", &unicode));
+    }
+
+    #[test]
+    fn test_char_classes_case_insensitive() {
+        let insensitive = MatchOptions {
+            case_sensitive: false,
+            .. MatchOptions::new()
+        };
+        let pat = Pattern::new("[[:upper:]]").unwrap();
+        assert!(!pat.matches("a"));
+        assert!(pat.matches_with("a", &insensitive));
+        assert!(!pat.matches_with("1", &insensitive));
+        let pat = Pattern::new("[![:lower:]]").unwrap();
+        assert!(pat.matches("A"));
+        assert!(!pat.matches_with("A", &insensitive));
+    }
+
+    #[test]
+    fn test_capture_char_class() {
+        let pat = Pattern::new("v([[:digit:]]*)/([[:alpha:]])*").unwrap();
+        let entry = pat.captures("v12.3/x.tar").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "12.3");
+        assert_eq!(entry.group(2).unwrap(), "x");
+        assert!(pat.captures("vx/x.tar").is_none());
+    }
+
     #[test]
     fn test_pattern_matches() {
         let txt_pat = Pattern::new("*hello.txt").unwrap();
@@ -1587,6 +3528,11 @@ mod test {
         let s = "_[_]_?_*_!_";
         assert_eq!(Pattern::escape(s), "_[[]_[]]_[?]_[*]_!_".to_string());
         assert!(Pattern::new(&Pattern::escape(s)).unwrap().matches(s));
+
+        let s = "(?<x>){1,2}";
+        assert_eq!(Pattern::escape(s), "[(][?]<x>[)][{]1,2}");
+        assert!(Pattern::new(&Pattern::escape(s)).unwrap().matches(s));
+        assert!(braces(&Pattern::escape(s)).unwrap().matches(s));
     }
 
     #[test]
@@ -1597,6 +3543,9 @@ mod test {
             case_sensitive: false,
             require_literal_separator: false,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
 
         assert!(pat.matches_with("aBcDeFg", &options));
@@ -1605,6 +3554,631 @@ mod test {
         assert!(pat.matches_with("AbCdEfG", &options));
     }
 
+    #[test]
+    fn test_pattern_matches_case_insensitive_unicode() {
+        let options = MatchOptions {
+            case_sensitive: false,
+            .. MatchOptions::new()
+        };
+
+        let pat = Pattern::new("Ärger/ΣΟΦΟΣ").unwrap();
+        assert!(pat.matches_with("ärger/σοφος", &options));
+        assert!(pat.matches_with("ÄRGER/ΣΟΦΟΣ", &options));
+        assert!(!pat.matches_with("ärger/σοφoς", &options));
+        assert!(!pat.matches("ärger/σοφος"));
+
+        assert!(Pattern::new("ß").unwrap().matches_with("ẞ", &options));
+        assert!(!Pattern::new("ss").unwrap().matches_with("ß", &options));
+        assert!(!Pattern::new("i").unwrap().matches_with("ı", &options));
+
+        let pat = Pattern::new("[а-я]").unwrap();
+        assert!(pat.matches_with("Ж", &options));
+        assert!(!pat.matches("Ж"));
+        let pat = Pattern::new("[!Ä-Ö]").unwrap();
+        assert!(!pat.matches_with("ö", &options));
+        assert!(pat.matches("ö"));
+    }
+
+    #[test]
+    fn test_capture_case_insensitive_unicode() {
+        let options = MatchOptions {
+            case_sensitive: false,
+            .. MatchOptions::new()
+        };
+        let pat = Pattern::new("(ä*)/ÉTÉ-(?).txt").unwrap();
+        let entry = pat.captures_with("Äpfel/été-Ω.TXT", &options).unwrap();
+        assert_eq!(entry.group(1).unwrap(), "Äpfel");
+        assert_eq!(entry.group(2).unwrap(), "Ω");
+        assert_eq!(entry.path().to_str().unwrap(), "Äpfel/été-Ω.TXT");
+    }
+
+    #[test]
+    fn test_pattern_matches_normalization_insensitive() {
+        let options = MatchOptions {
+            normalization_insensitive: true,
+            .. MatchOptions::new()
+        };
+        let nfc = "caf\u{e9}/cr\u{e8}me.txt";
+        let nfd = "cafe\u{301}/cre\u{300}me.txt";
+
+        let pat = Pattern::new(nfc).unwrap();
+        assert!(!pat.matches(nfd));
+        assert!(pat.matches_with(nfd, &options));
+        assert!(pat.matches_with(nfc, &options));
+
+        let pat = Pattern::new(nfd).unwrap();
+        assert!(!pat.matches(nfc));
+        assert!(pat.matches_with(nfc, &options));
+        assert!(pat.matches_with(nfd, &options));
+
+        // a decomposed character is still a single character
+        let pat = Pattern::new("caf?/*").unwrap();
+        assert!(!pat.matches_with("cafe\u{301}/x", &MatchOptions {
+            require_literal_separator: true,
+            .. MatchOptions::new()
+        }));
+        assert!(pat.matches_with("cafe\u{301}/x", &MatchOptions {
+            require_literal_separator: true,
+            normalization_insensitive: true,
+            .. MatchOptions::new()
+        }));
+
+        let pat = Pattern::new("[\u{e9}\u{e8}]").unwrap();
+        assert!(pat.matches_with("e\u{300}", &options));
+        assert!(!pat.matches_with("e", &options));
+    }
+
+    #[test]
+    fn test_capture_normalization_insensitive() {
+        let options = MatchOptions {
+            normalization_insensitive: true,
+            .. MatchOptions::new()
+        };
+        let pat = Pattern::new("(caf\u{e9})/(*).(?)").unwrap();
+        let path = "cafe\u{301}/cre\u{300}me.e\u{301}";
+        let entry = pat.captures_with(path, &options).unwrap();
+        assert_eq!(entry.path().to_str().unwrap(), path);
+        assert_eq!(entry.group(1).unwrap(), "cafe\u{301}");
+        assert_eq!(entry.group(2).unwrap(), "cre\u{300}me");
+        assert_eq!(entry.group(3).unwrap(), "e\u{301}");
+    }
+
+    #[test]
+    fn test_num_range() {
+        let pat = braces("shard-{1..20}.bin").unwrap();
+        assert!(pat.matches("shard-1.bin"));
+        assert!(pat.matches("shard-20.bin"));
+        assert!(!pat.matches("shard-0.bin"));
+        assert!(!pat.matches("shard-21.bin"));
+        assert!(!pat.matches("shard-01.bin"));
+        assert!(!pat.matches("shard-.bin"));
+
+        let pat = braces("shard-{00..63}").unwrap();
+        assert!(pat.matches("shard-00"));
+        assert!(pat.matches("shard-07"));
+        assert!(pat.matches("shard-63"));
+        assert!(!pat.matches("shard-7"));
+        assert!(!pat.matches("shard-64"));
+
+        // reversed ranges match the same numbers
+        assert!(braces("{10..5}").unwrap().matches("7"));
+        // the number is not necessarily followed by a non-digit
+        assert!(braces("{1..20}0").unwrap().matches("100"));
+        assert!(braces("{1..5}*").unwrap().matches("123"));
+
+        // anything else is literal
+        assert!(braces("{a..b}").unwrap().matches("{a..b}"));
+        assert!(braces("{1..}").unwrap().matches("{1..}"));
+        assert!(braces("{1.2}").unwrap().matches("{1.2}"));
+        assert!(braces("{1..2").unwrap().matches("{1..2"));
+
+        // and so are ranges unless enabled
+        let pat = Pattern::new("shard-{1..3}").unwrap();
+        assert!(pat.matches("shard-{1..3}"));
+        assert!(!pat.matches("shard-2"));
+    }
+
+    #[test]
+    fn test_capture_num_range() {
+        let pat = braces("data/shard-({00..63})/(*).bin").unwrap();
+        let entry = pat.captures("data/shard-42/x.bin").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "42");
+        assert_eq!(entry.group(2).unwrap(), "x");
+        assert!(pat.captures("data/shard-99/x.bin").is_none());
+    }
+
+    #[test]
+    fn test_substitute_num_range() {
+        let pat = braces("shard-({00..63})/(*).bin").unwrap();
+        assert_eq!(pat.substitute(&["07", "x"]).unwrap(), "shard-07/x.bin");
+        match pat.substitute(&["7", "x"]) {
+            Err(SubstitutionError::InvalidValue(0)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match pat.substitute(&["64", "x"]) {
+            Err(SubstitutionError::InvalidValue(0)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match braces("{1..3}").unwrap().substitute(&[]) {
+            Err(SubstitutionError::UnexpectedWildcard) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_expand() {
+        let pat = braces("a{1..3}/b{01..02}").unwrap();
+        assert_eq!(pat.expand().unwrap(), vec![
+            "a1/b01", "a1/b02", "a2/b01", "a2/b02", "a3/b01", "a3/b02",
+        ]);
+        assert_eq!(braces("{3..1}").unwrap().expand().unwrap(),
+                   vec!["3", "2", "1"]);
+        assert_eq!(braces("(x)").unwrap().expand().unwrap(),
+                   vec!["x"]);
+        assert!(braces("{1..3}/*").unwrap().expand().is_err());
+
+        let max = braces("{18446744073709551614..18446744073709551615}")
+            .unwrap();
+        assert_eq!(max.expand().unwrap(), vec![
+            "18446744073709551614", "18446744073709551615",
+        ]);
+        let max = braces("{18446744073709551615..18446744073709551615}")
+            .unwrap();
+        assert_eq!(max.expand().unwrap(), vec!["18446744073709551615"]);
+        assert!(max.matches("18446744073709551615"));
+    }
+
+    #[test]
+    fn test_braces() {
+        let pat = braces("src/*.{rs,toml}").unwrap();
+        assert!(pat.matches("src/lib.rs"));
+        assert!(pat.matches("src/Cargo.toml"));
+        assert!(!pat.matches("src/lib.rs.bak"));
+
+        let pat = braces("{a,b{c,d},{1..3}x,}z").unwrap();
+        for s in &["az", "bcz", "bdz", "2xz", "z"] {
+            assert!(pat.matches(s), "{}", s);
+        }
+        for s in &["bz", "4xz", "abz"] {
+            assert!(!pat.matches(s), "{}", s);
+        }
+
+        // `{,}` has two empty alternatives
+        assert!(braces("a{,}b").unwrap().matches("ab"));
+        // anything else is literal
+        assert!(braces("{a}").unwrap().matches("{a}"));
+        assert!(braces("{a,b").unwrap().matches("{a,b"));
+        assert!(braces("{}").unwrap().matches("{}"));
+        assert!(braces("[{]a,b}").unwrap().matches("{a,b}"));
+
+        assert_eq!(braces("a{b/c,d}").unwrap_err().pos, 3);
+        assert_eq!(braces("{a,**}").unwrap_err().pos, 3);
+
+        // braces are literal unless enabled
+        let pat = Pattern::new("a{1,2}b").unwrap();
+        assert!(pat.matches("a{1,2}b"));
+        assert!(!pat.matches("a1b"));
+        assert!(Pattern::new("a{b/c,d}").unwrap().matches("a{b/c,d}"));
+        let pat = Pattern::new_with("@(a{|b)", &PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        }).unwrap();
+        assert!(pat.matches("a{"));
+        assert!(pat.matches("b"));
+    }
+
+    #[test]
+    fn test_capture_braces() {
+        let pat = braces("({src,tests})/(*).rs").unwrap();
+        let entry = pat.captures("tests/lib.rs").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "tests");
+        assert_eq!(entry.group(2).unwrap(), "lib");
+        assert_eq!(pat.substitute(&["src", "x"]).unwrap(), "src/x.rs");
+
+        let pat = braces("{(a),b(c)}").unwrap();
+        let entry = pat.captures("bc").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "");
+        assert_eq!(entry.group(2).unwrap(), "c");
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        let pat = braces("out/{debug,release}/{x{1..2},y}").unwrap();
+        assert_eq!(pat.expand().unwrap(), vec![
+            "out/debug/x1", "out/debug/x2", "out/debug/y",
+            "out/release/x1", "out/release/x2", "out/release/y",
+        ]);
+        assert!(braces("{a,*}").unwrap().expand().is_err());
+
+        let pat = braces("{a,b}/{0..9}").unwrap();
+        assert_eq!(pat.expand_limit(20).unwrap().len(), 20);
+        match pat.expand_limit(19) {
+            Err(SubstitutionError::TooManyPaths(19)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match braces("{0..100000000}").unwrap().expand() {
+            Err(SubstitutionError::TooManyPaths(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let pat = Pattern::new_with("?(x)@(a|b)", &PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        }).unwrap();
+        assert_eq!(pat.expand().unwrap(), vec!["a", "b", "xa", "xb"]);
+    }
+
+    #[test]
+    fn test_captures_into() {
+        let mut spans = CaptureSpans::new();
+        let cases = [
+            ("(a)(b)(*)/(*)", "ab/c/d"),
+            ("(*).(??)", "file.rs"),
+            ("x/(**)/(*)", "x/y/z/w"),
+            ("({a,b}c)", "bc"),
+            ("p{1..9}", "p7"),
+        ];
+        for &(pattern, text) in &cases {
+            let pattern = braces(pattern).unwrap();
+            let entry = pattern.captures(text).unwrap();
+            assert!(pattern.captures_into(text, &mut spans));
+            for n in 0..pattern.group_count() + 2 {
+                assert_eq!(spans.group(text, n),
+                           entry.group(n).map(|g| g.to_str().unwrap()));
+            }
+        }
+        assert_eq!(spans.span(0), Some((0, 2)));
+        assert!(!Pattern::new("(*).rs").unwrap()
+                .captures_into("lib.md", &mut spans));
+
+        let options = MatchOptions {
+            normalization_insensitive: true,
+            .. MatchOptions::new()
+        };
+        let text = "cafe\u{301}/(x)";
+        let pattern = Pattern::new("caf\u{e9}/(*)").unwrap();
+        assert!(pattern.captures_into_with(text, &mut spans, &options));
+        assert_eq!(spans.group(text, 1), Some("(x)"));
+    }
+
+    #[test]
+    fn test_separator() {
+        let colon = MatchOptions {
+            separator: Separator::Char(':'),
+            require_literal_separator: true,
+            require_literal_leading_dot: true,
+            .. MatchOptions::new()
+        };
+        let pat = Pattern::new("a/*/c").unwrap();
+        assert!(pat.matches_with("a:b:c", &colon));
+        assert!(!pat.matches_with("a:b:x:c", &colon));
+        assert!(!pat.matches_with("a/b/c", &colon));
+        assert!(!Pattern::new("a?b").unwrap().matches_with("a:b", &colon));
+        assert!(Pattern::new("a:b").unwrap().matches_with("a:b", &colon));
+        assert!(!Pattern::new("*").unwrap().matches_with(".x", &colon));
+        assert!(!Pattern::new("a/*").unwrap().matches_with("a:.x", &colon));
+        assert!(Pattern::new("a/*").unwrap().matches_with("a:/.x", &colon));
+
+        let pat = Pattern::new("x/(**)/(*)").unwrap();
+        let mut spans = CaptureSpans::new();
+        assert!(pat.captures_into_with("x:p:q:y", &mut spans, &colon));
+        assert_eq!(spans.group("x:p:q:y", 1), Some("p:q"));
+        assert_eq!(spans.group("x:p:q:y", 2), Some("y"));
+        assert!(pat.captures_into_with("x:y", &mut spans, &colon));
+        assert_eq!(spans.group("x:y", 1), Some(""));
+
+        let ext = Pattern::new_with("a/@(*)", &PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        }).unwrap();
+        assert!(ext.matches_with("a:b", &colon));
+        assert!(!ext.matches_with("a:b:c", &colon));
+
+        let slash = MatchOptions {
+            separator: Separator::Slash,
+            require_literal_separator: true,
+            .. MatchOptions::new()
+        };
+        assert!(Pattern::new("a?b").unwrap().matches_with("a\\b", &slash));
+        assert!(!Pattern::new("a/b").unwrap().matches_with("a\\b", &slash));
+
+        let star = Pattern::new("*").unwrap();
+        assert_eq!(star.overlaps(&Pattern::new("a/b").unwrap(), &colon), None);
+        assert_eq!(star.overlaps(&Pattern::new("a/b").unwrap(), &slash), None);
+        assert!(Pattern::new("a/*").unwrap()
+                .is_subset_of_with(&Pattern::new("**/*").unwrap(), &colon));
+    }
+
+    #[test]
+    fn test_captures_key() {
+        let pat = Pattern::new("(*)/(*).(??)").unwrap();
+        let captures = pat.captures_key("users/alice.js").unwrap();
+        assert_eq!(captures.key(), "users/alice.js");
+        assert_eq!(captures.group(0), Some("users/alice.js"));
+        assert_eq!(captures.group(1), Some("users"));
+        assert_eq!(captures.group(2), Some("alice"));
+        assert_eq!(captures.group(3), Some("js"));
+        assert_eq!(captures.group(4), None);
+        assert!(pat.captures_key("alice.js").is_none());
+        // backslash is never a separator of keys
+        let captures = Pattern::new("(*)/(*)").unwrap()
+            .captures_key("a\\b/c").unwrap();
+        assert_eq!(captures.group(1), Some("a\\b"));
+    }
+
+    #[test]
+    fn test_named_groups() {
+        let pat = braces("(?<dir>**)/(?<name>*).(?<ext>{rs,md})").unwrap();
+        assert_eq!(pat.group_count(), 3);
+        assert_eq!(pat.group_index("dir"), Some(1));
+        assert_eq!(pat.group_index("ext"), Some(3));
+        assert_eq!(pat.group_index("other"), None);
+        let options = MatchOptions {
+            require_literal_separator: true,
+            .. MatchOptions::new()
+        };
+        let entry = pat.captures_with("src/bin/main.rs", &options).unwrap();
+        assert_eq!(entry.group(1).unwrap(), "src/bin");
+        assert_eq!(entry.group(2).unwrap(), "main");
+        assert_eq!(entry.group(3).unwrap(), "rs");
+
+        // not a valid name, so it's a plain group
+        let pat = Pattern::new("(?<a b>)").unwrap();
+        assert!(pat.matches("x<a b>"));
+        assert_eq!(pat.group_index("a b"), None);
+        // an escaped `?` keeps the group unnamed
+        let pat = Pattern::new("([?]<x>*)").unwrap();
+        assert_eq!(pat.captures("?<x>y").unwrap().group(1).unwrap(), "?<x>y");
+        assert_eq!(pat.group_index("x"), None);
+
+        assert_eq!(Pattern::new("(?<a>x)(?<a>y)").unwrap_err().pos, 7);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let options = MatchOptions::new();
+        let overlap = |a: &str, b: &str| {
+            braces(a).unwrap().overlaps(&braces(b).unwrap(), &options)
+        };
+        assert_eq!(overlap("docs/(*).md", "docs/(**)/index.md").unwrap(),
+                   "docs/index.md");
+        assert_eq!(overlap("*.rs", "lib*").unwrap(), "lib.rs");
+        assert_eq!(overlap("a[0-9]", "a{10..20}"), None);
+        assert_eq!(overlap("x{08..12}", "x1[!0]").unwrap(), "x11");
+        assert_eq!(overlap("{src,tests}/*", "*s/a").unwrap(), "src/s/a");
+        assert_eq!(overlap("a/**/b", "a/c"), None);
+        // `*` matches separators unless they are required to be literal
+        assert_eq!(overlap("*", "a/b").unwrap(), "a/b");
+        let literal = MatchOptions {
+            require_literal_separator: true,
+            .. MatchOptions::new()
+        };
+        assert_eq!(Pattern::new("*").unwrap()
+                   .overlaps(&Pattern::new("a/b").unwrap(), &literal), None);
+
+        let case_insensitive = MatchOptions {
+            case_sensitive: false,
+            .. MatchOptions::new()
+        };
+        let upper = Pattern::new("README.*").unwrap();
+        let lower = Pattern::new("readme.md").unwrap();
+        assert_eq!(upper.overlaps(&lower, &options), None);
+        assert_eq!(upper.overlaps(&lower, &case_insensitive).unwrap()
+                   .to_lowercase(), "readme.md");
+
+        let hidden = MatchOptions {
+            require_literal_leading_dot: true,
+            .. MatchOptions::new()
+        };
+        let any = Pattern::new("*/*").unwrap();
+        let dot = Pattern::new("x/.*").unwrap();
+        assert_eq!(any.overlaps(&dot, &options).unwrap(), "x/.");
+        assert_eq!(any.overlaps(&dot, &hidden), None);
+    }
+
+    #[test]
+    fn test_overlaps_extglob() {
+        let options = MatchOptions::new();
+        let ext = |s: &str| Pattern::new_with(s, &PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        }).unwrap();
+        assert_eq!(ext("!(*.txt)").overlaps(&ext("*.txt"), &options), None);
+        assert_eq!(ext("!(*.txt)").overlaps(&ext("*.t*"), &options).unwrap(),
+                   ".t");
+        assert_eq!(ext("+(ab)").overlaps(&ext("*b*b"), &options).unwrap(),
+                   "abab");
+        assert_eq!(ext("x/!(a|b)/y").overlaps(&ext("x/?/y"), &options)
+                   .unwrap(), "x/x/y");
+    }
+
+    #[test]
+    fn test_subset() {
+        let subset = |a: &str, b: &str| {
+            braces(a).unwrap().is_subset_of(&braces(b).unwrap())
+        };
+        assert!(subset("src/lib.rs", "src/*.rs"));
+        assert!(subset("src/*.rs", "**/*"));
+        assert!(subset("a{1..5}", "a[0-9]"));
+        assert!(subset("{a,b}c", "[ab]*"));
+        assert!(subset("*", "*"));
+        assert!(!subset("a{1..10}", "a[0-9]"));
+        assert!(!subset("src/*.rs", "src/[a-m]*"));
+        assert!(!subset("*", "?*"));
+
+        let literal = MatchOptions {
+            require_literal_separator: true,
+            .. MatchOptions::new()
+        };
+        let docs = Pattern::new("docs/*.md").unwrap();
+        let all = Pattern::new("**/*.md").unwrap();
+        assert!(docs.is_subset_of_with(&all, &literal));
+        assert!(!all.is_subset_of_with(&docs, &literal));
+
+        // letters without case are found among all of Unicode
+        let unicode = MatchOptions {
+            unicode_classes: true,
+            .. MatchOptions::new()
+        };
+        let alpha = Pattern::new("[[:alpha:]]").unwrap();
+        let cased = Pattern::new("[[:lower:][:upper:]]").unwrap();
+        assert!(alpha.is_subset_of(&cased));
+        assert!(!alpha.is_subset_of_with(&cased, &unicode));
+        assert!(cased.is_subset_of_with(&alpha, &unicode));
+        assert!(!Pattern::new("?").unwrap().is_subset_of_with(
+            &Pattern::new("[!\u{10ffff}]").unwrap(), &unicode));
+    }
+
+    #[test]
+    fn test_introspection() {
+        let pat = braces("/data/(shard-{00..63})/**/(*).bin").unwrap();
+        assert!(!pat.is_literal());
+        assert_eq!(pat.group_count(), 2);
+        assert_eq!(pat.literal_prefix(), Path::new("/data"));
+        assert_eq!(pat.literal_suffix(), ".bin");
+        assert_eq!(pat.required_extension(), Some("bin".to_string()));
+        assert_eq!(pat.depth(), (3, None));
+
+        let pat = Pattern::new("(src)/(lib).rs").unwrap();
+        assert!(pat.is_literal());
+        assert_eq!(pat.group_count(), 2);
+        assert_eq!(pat.literal_prefix(), Path::new("src"));
+        assert_eq!(pat.literal_suffix(), "src/lib.rs");
+        assert_eq!(pat.required_extension(), Some("rs".to_string()));
+        assert_eq!(pat.depth(), (2, Some(2)));
+
+        let pat = Pattern::new("**").unwrap();
+        assert_eq!(pat.literal_prefix(), Path::new(""));
+        assert_eq!(pat.literal_suffix(), "");
+        assert_eq!(pat.required_extension(), None);
+        assert_eq!(pat.depth(), (0, None));
+
+        let pat = Pattern::new("a/**/b/").unwrap();
+        assert_eq!(pat.literal_prefix(), Path::new("a"));
+        assert_eq!(pat.depth(), (2, None));
+
+        assert_eq!(Pattern::new("*.").unwrap().required_extension(), None);
+        assert_eq!(Pattern::new("*.d/x").unwrap().required_extension(), None);
+        assert_eq!(Pattern::new("*/.x").unwrap().required_extension(), None);
+        assert_eq!(Pattern::new("*.x").unwrap().required_extension(),
+                   Some("x".to_string()));
+        assert_eq!(Pattern::new("").unwrap().depth(), (0, Some(0)));
+        assert_eq!(Pattern::new("/").unwrap().literal_prefix(),
+                   Path::new("/"));
+        assert_eq!(Pattern::new_with("@(a|b)/?(c)", &PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        }).unwrap().group_count(), 2);
+    }
+
+    fn extglob(pattern: &str) -> Pattern {
+        let options = PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        };
+        Pattern::new_with(pattern, &options).unwrap()
+    }
+
+    #[test]
+    fn test_extglob_operators() {
+        let pat = extglob("a?(b|c)d");
+        assert!(pat.matches("ad"));
+        assert!(pat.matches("abd"));
+        assert!(pat.matches("acd"));
+        assert!(!pat.matches("abcd"));
+
+        let pat = extglob("a*(b|c)d");
+        assert!(pat.matches("ad"));
+        assert!(pat.matches("abcbd"));
+        assert!(!pat.matches("abxd"));
+
+        let pat = extglob("a+(b|cc)d");
+        assert!(!pat.matches("ad"));
+        assert!(pat.matches("abccbd"));
+        assert!(!pat.matches("abcd"));
+
+        let pat = extglob("*.@(jpg|png)");
+        assert!(pat.matches("cat.jpg"));
+        assert!(pat.matches("cat.png"));
+        assert!(!pat.matches("cat.gif"));
+        assert!(!pat.matches("cat.jpgpng"));
+
+        let pat = extglob("!(*.txt)");
+        assert!(pat.matches("notes.md"));
+        assert!(pat.matches(""));
+        assert!(!pat.matches("notes.txt"));
+        assert!(!pat.matches("dir/notes.md"));
+
+        let pat = extglob("@([[:digit:]]|x)?(-[!(])");
+        assert!(pat.matches("1-a"));
+        assert!(pat.matches("x"));
+        assert!(!pat.matches("1-("));
+
+        // without the option the operators are plain characters
+        assert!(Pattern::new("@(a)").unwrap().matches("@a"));
+        assert!(Pattern::new("*(a)").unwrap().matches("xa"));
+    }
+
+    #[test]
+    fn test_extglob_leading_dot() {
+        let options = MatchOptions {
+            require_literal_leading_dot: true,
+            .. MatchOptions::new()
+        };
+        let pat = extglob("!(a)");
+        assert!(pat.matches(".b"));
+        assert!(!pat.matches_with(".b", &options));
+        assert!(extglob("@(.b|c)").matches_with(".b", &options));
+    }
+
+    #[test]
+    fn test_extglob_errors() {
+        let options = PatternOptions {
+            extglob: true,
+            .. PatternOptions::new()
+        };
+        let err = Pattern::new_with("x@(a|b", &options).unwrap_err();
+        assert_eq!(err.pos, 2);
+        let err = Pattern::new_with("@(a|**)", &options).unwrap_err();
+        assert_eq!(err.pos, 4);
+        let err = Pattern::new_with("+(a/b)", &options).unwrap_err();
+        assert_eq!(err.pos, 3);
+        let err = Pattern::new_with("!([[:foo:]])", &options).unwrap_err();
+        assert_eq!(err.pos, 3);
+    }
+
+    #[test]
+    fn test_extglob_captures() {
+        let pat = extglob("(*).@(jpg|png)");
+        let entry = pat.captures("cat.png").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "cat");
+        assert_eq!(entry.group(2).unwrap(), "png");
+
+        let pat = extglob("+(@(a|b)(c))/(*)");
+        let entry = pat.captures("acbc/x").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "acbc");
+        // nested groups are from the last repetition
+        assert_eq!(entry.group(2).unwrap(), "b");
+        assert_eq!(entry.group(3).unwrap(), "c");
+        assert_eq!(entry.group(4).unwrap(), "x");
+
+        // groups of alternatives that didn't match are empty
+        let pat = extglob("@((a)|(b))");
+        let entry = pat.captures("b").unwrap();
+        assert_eq!(entry.group(2).unwrap(), "");
+        assert_eq!(entry.group(3).unwrap(), "b");
+        let entry = pat.captures("a").unwrap();
+        assert_eq!(entry.group(2).unwrap(), "a");
+        assert_eq!(entry.group(3).unwrap(), "");
+
+        let pat = extglob("?(x)(*)");
+        let entry = pat.captures("yz").unwrap();
+        assert_eq!(entry.group(1).unwrap(), "");
+        assert_eq!(entry.group(2).unwrap(), "yz");
+
+        assert_eq!(pat.substitute(&["x", "y"]).unwrap(), "xy");
+    }
+
     #[test]
     fn test_pattern_matches_case_insensitive_range() {
 
@@ -1615,11 +4189,17 @@ mod test {
             case_sensitive: false,
             require_literal_separator: false,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
         let options_case_sensitive = MatchOptions {
             case_sensitive: true,
             require_literal_separator: false,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
 
         assert!(pat_within.matches_with("a", &options_case_insensitive));
@@ -1638,11 +4218,17 @@ mod test {
             case_sensitive: true,
             require_literal_separator: true,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
         let options_not_require_literal = MatchOptions {
             case_sensitive: true,
             require_literal_separator: false,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
 
         assert!(Pattern::new("abc/def").unwrap().matches_with("abc/def", &options_require_literal));
@@ -1677,11 +4263,17 @@ mod test {
             case_sensitive: true,
             require_literal_separator: false,
             require_literal_leading_dot: true,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
         let options_not_require_literal_leading_dot = MatchOptions {
             case_sensitive: true,
             require_literal_separator: false,
             require_literal_leading_dot: false,
+            normalization_insensitive: false,
+            unicode_classes: false,
+            separator: Separator::Platform,
         };
 
         let f = |options| Pattern::new("*.txt").unwrap().matches_with(".hello.txt", options);
@@ -1723,12 +4315,12 @@ mod test {
     fn test_matches_path() {
         // on windows, (Path::new("a/b").as_str().unwrap() == "a\\b"), so this
         // tests that / and \ are considered equivalent on windows
-        assert!(Pattern::new("a/b").unwrap().matches_path(&Path::new("a/b")));
+        assert!(Pattern::new("a/b").unwrap().matches_path(Path::new("a/b")));
     }
 
     #[test]
     fn test_path_join() {
-        let pattern = Path::new("one").join(&Path::new("**/*.rs"));
+        let pattern = Path::new("one").join(Path::new("**/*.rs"));
         assert!(Pattern::new(pattern.to_str().unwrap()).is_ok());
     }
 
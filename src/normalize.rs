@@ -0,0 +1,206 @@
+//! Support for `MatchOptions::normalization_insensitive`
+//!
+//! Both the pattern and the matched string are converted to NFC before
+//! matching. Since capture groups must point into the original string, the
+//! string is normalized in independent segments and the offsets of segment
+//! boundaries are remembered.
+
+use unicode_normalization::{UnicodeNormalization, is_nfc};
+use unicode_normalization::char::canonical_combining_class;
+
+use {PatternToken, CharSpecifier};
+
+
+/// A string converted to NFC along with the offsets into the original one
+pub(crate) struct Normalized {
+    text: String,
+    // (offset in normalized text, offset in original text) of every segment
+    // boundary, sorted
+    boundaries: Vec<(usize, usize)>,
+}
+
+impl Normalized {
+    /// Returns `None` if the string is already in NFC form
+    pub(crate) fn new(s: &str) -> Option<Normalized> {
+        if is_nfc(s) {
+            return None;
+        }
+        let mut text = String::with_capacity(s.len());
+        let mut boundaries = Vec::new();
+        // the segment that may still compose with the following characters
+        let mut cur_start = 0;
+        let mut cur_nfc = String::new();
+        for (start, end) in starter_segments(s) {
+            let seg_nfc = s[start..end].nfc().collect::<String>();
+            let joined = s[cur_start..end].nfc().collect::<String>();
+            let independent = cur_start != start &&
+                joined.len() == cur_nfc.len() + seg_nfc.len() &&
+                joined.starts_with(&cur_nfc[..]) &&
+                joined.ends_with(&seg_nfc[..]);
+            if !independent {
+                // characters compose across the boundary (or this is the
+                // first segment), so they must be treated as a single unit
+                cur_nfc = joined;
+            } else {
+                boundaries.push((text.len(), cur_start));
+                text.push_str(&cur_nfc);
+                cur_start = start;
+                cur_nfc = seg_nfc;
+            }
+        }
+        boundaries.push((text.len(), cur_start));
+        text.push_str(&cur_nfc);
+        boundaries.push((text.len(), s.len()));
+        Some(Normalized { text, boundaries })
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Converts an offset in the normalized text into the offset in the
+    /// original one
+    ///
+    /// Offsets that point into the middle of a segment are moved to its start.
+    pub(crate) fn original_offset(&self, off: usize) -> usize {
+        match self.boundaries.binary_search_by_key(&off, |&(n, _)| n) {
+            Ok(idx) => self.boundaries[idx].1,
+            Err(idx) => self.boundaries[idx - 1].1,
+        }
+    }
+}
+
+// Splits the string into pieces each starting with a character of canonical
+// combining class zero
+fn starter_segments(s: &str) -> Vec<(usize, usize)> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    for (idx, c) in s.char_indices() {
+        if idx > start && canonical_combining_class(c) == 0 {
+            result.push((start, idx));
+            start = idx;
+        }
+    }
+    if start < s.len() {
+        result.push((start, s.len()));
+    }
+    result
+}
+
+/// Returns the tokens with literal characters converted to NFC, or `None`
+/// if normalization doesn't change anything
+pub(crate) fn normalize_tokens(tokens: &[PatternToken])
+    -> Option<Vec<PatternToken>>
+{
+    use PatternToken::*;
+
+    if tokens.iter().all(is_ascii_token) {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut run = String::new();
+    for token in tokens {
+        match *token {
+            Char(c) => {
+                run.push(c);
+                continue;
+            }
+            AnyWithin(ref specs) => {
+                result.extend(run.nfc().map(Char));
+                result.push(AnyWithin(normalize_specifiers(specs)));
+            }
+            AnyExcept(ref specs) => {
+                result.extend(run.nfc().map(Char));
+                result.push(AnyExcept(normalize_specifiers(specs)));
+            }
+            ExtGlob(kind, ref alternatives, capture) => {
+                result.extend(run.nfc().map(Char));
+                let alternatives = alternatives.iter()
+                    .map(|alt| normalize_tokens(alt)
+                               .unwrap_or_else(|| alt.clone()))
+                    .collect();
+                result.push(ExtGlob(kind, alternatives, capture));
+            }
+            ref token => {
+                result.extend(run.nfc().map(Char));
+                result.push(token.clone());
+            }
+        }
+        run.clear();
+    }
+    result.extend(run.nfc().map(Char));
+    if &result[..] == tokens {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+fn is_ascii_token(token: &PatternToken) -> bool {
+    use PatternToken::*;
+
+    match *token {
+        Char(c) => c.is_ascii(),
+        AnyWithin(ref specs) | AnyExcept(ref specs) => {
+            specs.iter().all(|s| match *s {
+                CharSpecifier::SingleChar(c) => c.is_ascii(),
+                _ => true,
+            })
+        }
+        ExtGlob(_, ref alternatives, _) => {
+            alternatives.iter().all(|alt| alt.iter().all(is_ascii_token))
+        }
+        _ => true,
+    }
+}
+
+fn normalize_specifiers(specs: &[CharSpecifier]) -> Vec<CharSpecifier> {
+    use CharSpecifier::SingleChar;
+
+    let mut result = Vec::with_capacity(specs.len());
+    let mut run = String::new();
+    for spec in specs {
+        if let SingleChar(c) = *spec {
+            run.push(c);
+        } else {
+            result.extend(run.nfc().map(SingleChar));
+            run.clear();
+            result.push(*spec);
+        }
+    }
+    result.extend(run.nfc().map(SingleChar));
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::Normalized;
+
+    #[test]
+    fn already_normalized() {
+        assert!(Normalized::new("caf\u{e9}").is_none());
+        assert!(Normalized::new("plain").is_none());
+    }
+
+    #[test]
+    fn offsets() {
+        let orig = "cafe\u{301}/x";
+        let norm = Normalized::new(orig).unwrap();
+        assert_eq!(norm.as_str(), "caf\u{e9}/x");
+        // start of `/`
+        assert_eq!(norm.original_offset(5), 6);
+        assert_eq!(norm.original_offset(norm.as_str().len()), orig.len());
+        // in the middle of `é`
+        assert_eq!(norm.original_offset(4), 3);
+    }
+
+    #[test]
+    fn hangul() {
+        // L + V + T jamo compose into a single syllable
+        let orig = "\u{1100}\u{1161}\u{11a8}!";
+        let norm = Normalized::new(orig).unwrap();
+        assert_eq!(norm.as_str(), "\u{ac01}!");
+        assert_eq!(norm.original_offset(3), 9);
+    }
+}
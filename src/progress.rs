@@ -0,0 +1,60 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+
+/// Statistics of a glob walk, see `Entries::on_progress()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    /// Number of directories listed so far
+    pub dirs_read: usize,
+    /// Number of paths checked against the pattern so far
+    pub entries_examined: usize,
+    /// Number of matching paths yielded so far
+    pub matches: usize,
+}
+
+/// A flag that stops a glob walk, possibly from another thread
+///
+/// Clones of the token share the flag, so one clone is passed to
+/// `Entries::cancel_token()` and another one is used to cancel.
+///
+/// # Examples
+///
+/// ```rust
+/// use capturing_glob::{glob, CancelToken};
+///
+/// let token = CancelToken::new();
+/// let mut entries = glob("src/**/*.rs").unwrap()
+///     .cancel_token(token.clone());
+/// token.cancel();
+/// assert!(entries.next().unwrap().unwrap_err().is_cancelled());
+/// assert!(entries.next().is_none());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    flag: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    /// Create a token that is not cancelled yet
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+    /// Request the walks using this token to stop
+    pub fn cancel(&self) {
+        self.flag.store(true, Ordering::Relaxed);
+    }
+    /// Return true if `cancel()` was called on this token or its clones
+    pub fn is_cancelled(&self) -> bool {
+        self.flag.load(Ordering::Relaxed)
+    }
+}
+
+pub(crate) struct ProgressCallback(pub Box<dyn FnMut(&Progress) + Send>);
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
@@ -0,0 +1,337 @@
+//! Bulk renaming of the matched paths, like `mmv`
+
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use {glob_with, GlobError, MatchOptions, Pattern, PatternError};
+use SubstitutionError;
+
+
+/// A single step of a `RenamePlan`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rename {
+    /// The path to move
+    pub from: PathBuf,
+    /// The new path
+    pub to: PathBuf,
+}
+
+/// A list of renames that can be applied safely
+///
+/// The renames are ordered so that no path is overwritten: for the chains
+/// like `a → b, b → c` the latter is done first, and the cycles like
+/// `a → b, b → a` are broken by moving a path to a temporary name first.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use capturing_glob::{plan_rename, MatchOptions};
+///
+/// let plan = plan_rename("img_(*).JPG", "photos/(*).jpg",
+///                        &MatchOptions::new()).unwrap();
+/// for step in plan.steps() {   // dry run
+///     println!("{:?} -> {:?}", step.from, step.to);
+/// }
+/// plan.apply().unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenamePlan {
+    steps: Vec<Rename>,
+}
+
+/// An error of planning or applying renames
+#[derive(Debug)]
+pub enum RenameError {
+    /// The source pattern is invalid
+    Pattern(PatternError),
+    /// A directory can't be read
+    Glob(GlobError),
+    /// The target can't be made from the captures of the path
+    Substitution(PathBuf, SubstitutionError),
+    /// The same path would be moved to several targets
+    DuplicateSource {
+        /// The common source
+        source: PathBuf,
+        /// Two of the targets
+        targets: (PathBuf, PathBuf),
+    },
+    /// Several paths would be moved to the same target
+    Collision {
+        /// The common target
+        target: PathBuf,
+        /// Two of the sources
+        sources: (PathBuf, PathBuf),
+    },
+    /// The target exists and is not moved away by the plan
+    TargetExists(PathBuf),
+    /// A rename failed, the ones done before were reverted
+    Io {
+        /// The failed step
+        rename: Rename,
+        /// The error of the step
+        error: io::Error,
+    },
+    /// A rename failed and reverting the ones done before failed too, so
+    /// the file system is left half-renamed
+    RollbackFailed {
+        /// The failed step
+        rename: Rename,
+        /// The error of the step
+        error: io::Error,
+        /// The step that couldn't be reverted
+        rollback: Rename,
+        /// The error of reverting
+        rollback_error: io::Error,
+    },
+}
+
+/// Glob the source pattern and plan moving the matched paths to the target
+/// pattern, filled with the capture groups by `Pattern::substitute()`
+///
+/// Matching is done like in `glob_with()`. The plan is checked for
+/// collisions with each other and with the existing files, but nothing is
+/// renamed until `RenamePlan::apply()` is called.
+pub fn plan_rename(source: &str, target: &str, options: &MatchOptions)
+    -> Result<RenamePlan, RenameError>
+{
+    let target = Pattern::new(target)?;
+    let mut moves = Vec::new();
+    for entry in glob_with(source, options)? {
+        let entry = entry?;
+        let groups = (1..target.group_count() + 1)
+            .map(|n| entry.group(n).and_then(|g| g.to_str()).unwrap_or(""))
+            .collect::<Vec<_>>();
+        let to = target.substitute(&groups).map_err(|e| {
+            RenameError::Substitution(entry.path().to_path_buf(), e)
+        })?;
+        moves.push(Rename {
+            from: entry.path().to_path_buf(),
+            to: PathBuf::from(to),
+        });
+    }
+    RenamePlan::new(moves)
+}
+
+impl RenamePlan {
+    /// Order the renames, breaking the cycles
+    ///
+    /// Renames to the same path are dropped. Fails if two renames have
+    /// the same source or the same target, or if a target exists and is not
+    /// renamed itself.
+    pub fn new<I>(moves: I) -> Result<RenamePlan, RenameError>
+        where I: IntoIterator<Item=Rename>,
+    {
+        let moves = moves.into_iter()
+            .filter(|m| m.from != m.to)
+            .collect::<Vec<_>>();
+        let mut sources = HashMap::new();
+        for (idx, m) in moves.iter().enumerate() {
+            if let Some(other) = sources.insert(&m.from, idx) {
+                return Err(RenameError::DuplicateSource {
+                    source: m.from.clone(),
+                    targets: (moves[other].to.clone(), m.to.clone()),
+                });
+            }
+        }
+        let mut targets = HashMap::<&PathBuf, &PathBuf>::new();
+        for m in &moves {
+            if let Some(other) = targets.insert(&m.to, &m.from) {
+                return Err(RenameError::Collision {
+                    target: m.to.clone(),
+                    sources: (other.clone(), m.from.clone()),
+                });
+            }
+            if !sources.contains_key(&m.to) &&
+                fs::symlink_metadata(&m.to).is_ok()
+            {
+                return Err(RenameError::TargetExists(m.to.clone()));
+            }
+        }
+
+        // Each path is the source and the target of at most one rename,
+        // so the renames form chains and cycles. `next[i]` is the rename
+        // that has to be done before `i` to vacate its target.
+        let next = moves.iter()
+            .map(|m| sources.get(&m.to).cloned())
+            .collect::<Vec<_>>();
+        let mut has_prev = vec![false; moves.len()];
+        for &n in next.iter().flatten() {
+            has_prev[n] = true;
+        }
+        let mut done = vec![false; moves.len()];
+        let mut steps = Vec::with_capacity(moves.len());
+        for start in (0..moves.len()).filter(|&idx| !has_prev[idx]) {
+            let mut chain = Vec::new();
+            let mut cur = Some(start);
+            while let Some(idx) = cur {
+                chain.push(idx);
+                done[idx] = true;
+                cur = next[idx];
+            }
+            steps.extend(chain.iter().rev().map(|&idx| moves[idx].clone()));
+        }
+        let mut temporary = BTreeSet::new();
+        for start in 0..moves.len() {
+            if done[start] {
+                continue;
+            }
+            let tmp = temporary_name(&moves[start].from, &mut temporary);
+            steps.push(Rename { from: moves[start].from.clone(),
+                                to: tmp.clone() });
+            let mut cycle = Vec::new();
+            let mut cur = next[start];
+            while let Some(idx) = cur.filter(|&idx| idx != start) {
+                cycle.push(idx);
+                done[idx] = true;
+                cur = next[idx];
+            }
+            done[start] = true;
+            steps.extend(cycle.iter().rev().map(|&idx| moves[idx].clone()));
+            steps.push(Rename { from: tmp, to: moves[start].to.clone() });
+        }
+        Ok(RenamePlan { steps })
+    }
+
+    /// The renames in the order they are applied, i.e. a dry run
+    pub fn steps(&self) -> &[Rename] {
+        &self.steps
+    }
+
+    /// Return true if there is nothing to rename
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    /// Rename the paths, creating the missing parent directories of the
+    /// targets
+    ///
+    /// If a rename fails, the ones done so far are reverted. Targets are
+    /// checked again before renaming, so a path created after the plan was
+    /// made is not overwritten either. The created directories are kept.
+    pub fn apply(&self) -> Result<(), RenameError> {
+        for (idx, step) in self.steps.iter().enumerate() {
+            if let Err(error) = rename(step) {
+                for done in self.steps[..idx].iter().rev() {
+                    let back = Rename { from: done.to.clone(),
+                                        to: done.from.clone() };
+                    if let Err(rollback_error) = fs::rename(&back.from,
+                                                            &back.to)
+                    {
+                        return Err(RenameError::RollbackFailed {
+                            rename: step.clone(),
+                            error,
+                            rollback: back,
+                            rollback_error,
+                        });
+                    }
+                }
+                return Err(RenameError::Io { rename: step.clone(), error });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn rename(step: &Rename) -> io::Result<()> {
+    if fs::symlink_metadata(&step.to).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists,
+                                  "target already exists"));
+    }
+    if let Some(parent) = step.to.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::rename(&step.from, &step.to)
+}
+
+// A name next to `path` that neither exists nor is used by the plan
+fn temporary_name(path: &Path, used: &mut BTreeSet<PathBuf>) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".rename-tmp");
+    for n in 0.. {
+        let mut candidate = name.clone();
+        if n > 0 {
+            candidate.push(format!("-{}", n));
+        }
+        let candidate = path.with_file_name(candidate);
+        if !used.contains(&candidate) &&
+            fs::symlink_metadata(&candidate).is_err()
+        {
+            used.insert(candidate.clone());
+            return candidate;
+        }
+    }
+    unreachable!();
+}
+
+impl From<PatternError> for RenameError {
+    fn from(e: PatternError) -> RenameError {
+        RenameError::Pattern(e)
+    }
+}
+
+impl From<GlobError> for RenameError {
+    fn from(e: GlobError) -> RenameError {
+        RenameError::Glob(e)
+    }
+}
+
+impl Error for RenameError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use self::RenameError::*;
+        match *self {
+            Pattern(ref e) => Some(e),
+            Glob(ref e) => Some(e),
+            Substitution(_, ref e) => Some(e),
+            DuplicateSource { .. } | Collision { .. } | TargetExists(_) => {
+                None
+            }
+            Io { ref error, .. } => Some(error),
+            RollbackFailed { ref error, .. } => Some(error),
+        }
+    }
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::RenameError::*;
+        match *self {
+            Pattern(ref e) => e.fmt(f),
+            Glob(ref e) => e.fmt(f),
+            Substitution(ref path, ref e) => {
+                write!(f, "can't make the target of `{}`: {}",
+                       path.display(), e)
+            }
+            DuplicateSource { ref source, targets: (ref a, ref b) } => {
+                write!(f, "`{}` would be moved to both `{}` and `{}`",
+                       source.display(), a.display(), b.display())
+            }
+            Collision { ref target, sources: (ref a, ref b) } => {
+                write!(f, "both `{}` and `{}` would be moved to `{}`",
+                       a.display(), b.display(), target.display())
+            }
+            TargetExists(ref path) => {
+                write!(f, "`{}` already exists", path.display())
+            }
+            Io { ref rename, ref error } => {
+                write!(f, "can't move `{}` to `{}`: {}, reverted the \
+                           previous renames",
+                       rename.from.display(), rename.to.display(), error)
+            }
+            RollbackFailed { ref rename, ref error, ref rollback,
+                             ref rollback_error } =>
+            {
+                write!(f, "can't move `{}` to `{}`: {}, and can't move \
+                           `{}` back to `{}`: {}",
+                       rename.from.display(), rename.to.display(), error,
+                       rollback.from.display(), rollback.to.display(),
+                       rollback_error)
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Implementations of serde traits, enabled by the `serde` feature
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeStruct, Serializer};
+
+use {Entry, Pattern};
+
+
+/// Serialized as the original pattern string
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+/// Deserialized from a string, which is validated by `Pattern::new()`
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D)
+        -> Result<Pattern, D::Error>
+    {
+        deserializer.deserialize_str(PatternVisitor)
+    }
+}
+
+struct PatternVisitor;
+
+impl<'de> Visitor<'de> for PatternVisitor {
+    type Value = Pattern;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a glob pattern")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Pattern, E> {
+        Pattern::new(value).map_err(|e| {
+            E::custom(format_args!("invalid glob pattern {:?}: {}", value, e))
+        })
+    }
+}
+
+/// Serialized as `{"path": .., "groups": [..]}`, with a `names` map from
+/// the group names to the values added if the pattern has named groups
+///
+/// Fails if the path is not valid UTF-8.
+impl Serialize for Entry {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        let groups = (1..self.spans().len() + 1)
+            .map(|n| self.group(n).and_then(|g| g.to_str()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ser::Error::custom(
+                "capture group is not valid UTF-8"))?;
+        let named = self.names().is_some();
+        let mut state = serializer.serialize_struct("Entry",
+            if named { 3 } else { 2 })?;
+        state.serialize_field("path", self.path())?;
+        state.serialize_field("groups", &groups)?;
+        if named {
+            state.serialize_field("names", &Names(self, &groups))?;
+        }
+        state.end()
+    }
+}
+
+struct Names<'a>(&'a Entry, &'a [&'a str]);
+
+impl<'a> Serialize for Names<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        let mut map = serializer.serialize_map(None)?;
+        for (n, value) in self.1.iter().enumerate() {
+            if let Some(name) = self.0.group_name(n + 1) {
+                map.serialize_entry(name, value)?;
+            }
+        }
+        map.end()
+    }
+}
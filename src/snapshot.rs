@@ -0,0 +1,333 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use {glob_in, Entry, FileSystem, GlobError, MatchOptions, PatternError, StdFs};
+
+
+/// The paths matched by a glob along with their captures, modification
+/// times and sizes
+///
+/// Snapshots are meant to be stored between the runs of a program (with
+/// the `serde` feature) and compared with `diff()`.
+///
+/// # Examples
+///
+/// ```rust
+/// use capturing_glob::{GlobSnapshot, MatchOptions, diff};
+///
+/// let options = MatchOptions::new();
+/// let old = GlobSnapshot::new("src/(*).rs", &options).unwrap();
+/// // ... build ...
+/// let new = old.update("src/(*).rs", &options).unwrap();
+/// for entry in diff(&old, &new).added {
+///     println!("new module {:?}", entry.entry().group(1).unwrap());
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GlobSnapshot {
+    entries: Vec<SnapshotEntry>,
+    dirs: BTreeMap<PathBuf, SnapshotDir>,
+}
+
+/// A path recorded in a `GlobSnapshot`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde",
+           serde(try_from = "RawEntry", into = "RawEntry"))]
+pub struct SnapshotEntry {
+    entry: Entry,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+// Listing of a directory read while taking the snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct SnapshotDir {
+    modified: SystemTime,
+    names: Vec<OsString>,
+}
+
+/// The difference between two snapshots, as returned by `diff()`
+///
+/// Each list is sorted by path.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotDiff<'a> {
+    /// Paths that are only in the new snapshot
+    pub added: Vec<&'a SnapshotEntry>,
+    /// Paths that are only in the old snapshot
+    pub removed: Vec<&'a SnapshotEntry>,
+    /// Paths whose modification time, size or captures differ, as recorded
+    /// in the new snapshot
+    pub changed: Vec<&'a SnapshotEntry>,
+}
+
+/// An error of taking a `GlobSnapshot`
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The pattern is invalid
+    Pattern(PatternError),
+    /// A directory can't be read
+    Glob(GlobError),
+}
+
+impl GlobSnapshot {
+    /// Glob the pattern and record the matched paths
+    ///
+    /// Matching is done like in `glob_with()`, the first error of reading a
+    /// directory fails the snapshot.
+    pub fn new(pattern: &str, options: &MatchOptions)
+        -> Result<GlobSnapshot, SnapshotError>
+    {
+        GlobSnapshot::take(pattern, options, None)
+    }
+
+    /// Glob the pattern again, reusing the listings of the directories
+    /// whose modification time has not changed since this snapshot
+    ///
+    /// The pattern may differ from the one used for this snapshot. Adding
+    /// or removing entries of a directory updates its modification time, but
+    /// its resolution depends on the file system, so the changes made within
+    /// the same tick as the previous snapshot may be missed.
+    pub fn update(&self, pattern: &str, options: &MatchOptions)
+        -> Result<GlobSnapshot, SnapshotError>
+    {
+        GlobSnapshot::take(pattern, options, Some(self))
+    }
+
+    fn take(pattern: &str, options: &MatchOptions,
+            previous: Option<&GlobSnapshot>)
+        -> Result<GlobSnapshot, SnapshotError>
+    {
+        let fs = CachedFs {
+            previous,
+            dirs: RefCell::new(BTreeMap::new()),
+        };
+        let mut entries = Vec::new();
+        for entry in glob_in(&fs, pattern, options)? {
+            let entry = entry?;
+            let metadata = match fs::metadata(entry.path()) {
+                Ok(metadata) => metadata,
+                // removed after its directory was read
+                Err(_) => continue,
+            };
+            entries.push(SnapshotEntry {
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+                entry,
+            });
+        }
+        entries.sort_by(|a, b| a.path().cmp(b.path()));
+        entries.dedup_by(|a, b| a.path() == b.path());
+        Ok(GlobSnapshot {
+            entries,
+            dirs: fs.dirs.into_inner(),
+        })
+    }
+
+    /// Return the recorded paths, sorted
+    pub fn entries(&self) -> &[SnapshotEntry] {
+        &self.entries
+    }
+
+    /// Return the recorded path, if it was matched
+    pub fn get<P: AsRef<Path>>(&self, path: P) -> Option<&SnapshotEntry> {
+        let path = path.as_ref();
+        self.entries.binary_search_by(|e| e.path().cmp(path))
+            .ok().map(|idx| &self.entries[idx])
+    }
+}
+
+impl SnapshotEntry {
+    /// The path along with the capture groups
+    pub fn entry(&self) -> &Entry {
+        &self.entry
+    }
+    /// The path
+    pub fn path(&self) -> &Path {
+        self.entry.path()
+    }
+    /// Modification time, if supported by the platform
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+    /// Size of the file in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+    /// Return true if the file is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<'a> SnapshotDiff<'a> {
+    /// Return true if nothing has changed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() &&
+            self.changed.is_empty()
+    }
+}
+
+/// Compare two snapshots of the same glob, usually taken at different
+/// times
+pub fn diff<'a>(old: &'a GlobSnapshot, new: &'a GlobSnapshot)
+    -> SnapshotDiff<'a>
+{
+    let mut result = SnapshotDiff::default();
+    let mut old_iter = old.entries.iter().peekable();
+    let mut new_iter = new.entries.iter().peekable();
+    loop {
+        match (old_iter.peek(), new_iter.peek()) {
+            (Some(o), Some(n)) if o.path() < n.path() => {
+                result.removed.push(o);
+                old_iter.next();
+            }
+            (Some(o), Some(n)) if o.path() > n.path() => {
+                result.added.push(n);
+                new_iter.next();
+            }
+            (Some(o), Some(n)) => {
+                if o != n {
+                    result.changed.push(n);
+                }
+                old_iter.next();
+                new_iter.next();
+            }
+            (Some(o), None) => {
+                result.removed.push(o);
+                old_iter.next();
+            }
+            (None, Some(n)) => {
+                result.added.push(n);
+                new_iter.next();
+            }
+            (None, None) => break,
+        }
+    }
+    result
+}
+
+// The real file system that records the directory listings and reuses
+// the ones of the previous snapshot
+struct CachedFs<'a> {
+    previous: Option<&'a GlobSnapshot>,
+    dirs: RefCell<BTreeMap<PathBuf, SnapshotDir>>,
+}
+
+impl<'a> FileSystem for CachedFs<'a> {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        let modified = match fs::metadata(path).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return StdFs.read_dir(path),
+        };
+        let cached = self.previous
+            .and_then(|s| s.dirs.get(path))
+            .filter(|dir| dir.modified == modified);
+        let names = match cached {
+            Some(dir) => dir.names.clone(),
+            None => {
+                let entries = StdFs.read_dir(path)?;
+                if entries.iter().any(|e| e.is_err()) {
+                    return Ok(entries);
+                }
+                entries.into_iter().filter_map(|e| e.ok()).collect()
+            }
+        };
+        self.dirs.borrow_mut().insert(path.to_path_buf(), SnapshotDir {
+            modified,
+            names: names.clone(),
+        });
+        Ok(names.into_iter().map(Ok).collect())
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        StdFs.is_dir(path)
+    }
+    fn exists(&self, path: &Path) -> bool {
+        StdFs.exists(path)
+    }
+}
+
+impl From<PatternError> for SnapshotError {
+    fn from(e: PatternError) -> SnapshotError {
+        SnapshotError::Pattern(e)
+    }
+}
+
+impl From<GlobError> for SnapshotError {
+    fn from(e: GlobError) -> SnapshotError {
+        SnapshotError::Glob(e)
+    }
+}
+
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            SnapshotError::Pattern(ref e) => Some(e),
+            SnapshotError::Glob(ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SnapshotError::Pattern(ref e) => e.fmt(f),
+            SnapshotError::Glob(ref e) => e.fmt(f),
+        }
+    }
+}
+
+// Serialized form of `SnapshotEntry`, capture groups are byte ranges of
+// the path
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct RawEntry {
+    path: PathBuf,
+    groups: Vec<(usize, usize)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    names: Option<Vec<Option<String>>>,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+#[cfg(feature = "serde")]
+impl From<SnapshotEntry> for RawEntry {
+    fn from(e: SnapshotEntry) -> RawEntry {
+        RawEntry {
+            groups: e.entry.spans().to_vec(),
+            names: e.entry.names().map(|names| names.to_vec()),
+            path: e.entry.into(),
+            modified: e.modified,
+            len: e.len,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::std::convert::TryFrom<RawEntry> for SnapshotEntry {
+    type Error = String;
+    fn try_from(e: RawEntry) -> Result<SnapshotEntry, String> {
+        let len = e.path.as_os_str().len();
+        if e.groups.iter().any(|&(a, b)| a > b || b > len) {
+            return Err(format!("invalid capture group of {:?}", e.path));
+        }
+        Ok(SnapshotEntry {
+            entry: match e.names {
+                Some(names) => Entry::with_captures(e.path, e.groups)
+                    .with_names(&names.into()),
+                None => Entry::with_captures(e.path, e.groups),
+            },
+            modified: e.modified,
+            len: e.len,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp::{Ordering, Reverse};
+
+use {Entry, MatchOptions, Pattern, PatternToken};
+use ast::ExtGlobKind;
+
+
+/// How specific a pattern is, as returned by `Pattern::specificity()`
+///
+/// The greater value is the more specific one. Patterns are compared by the
+/// following criteria, in order:
+///
+/// 1. More literal characters win (only characters that every matched path
+///    contains are counted, e.g. the shortest alternative of `{a,bc}`)
+/// 2. Fewer recursive wildcards `**` win
+/// 3. Fewer `*` wildcards win (extended glob operators other than `@(...)`
+///    are counted as `*` too)
+/// 4. Fewer `?` wildcards win
+/// 5. Fewer character classes and numeric ranges win
+///
+/// So for example `src/lib.rs` > `src/*.rs` > `src/*` > `**/*.rs`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Specificity {
+    literals: usize,
+    recursive: usize,
+    sequences: usize,
+    any_chars: usize,
+    classes: usize,
+}
+
+impl Specificity {
+    pub(crate) fn of(tokens: &[PatternToken]) -> Specificity {
+        use PatternToken::*;
+
+        let mut result = Specificity::default();
+        for token in tokens {
+            match *token {
+                Char(_) => result.literals += 1,
+                AnyChar => result.any_chars += 1,
+                AnySequence => result.sequences += 1,
+                AnyRecursiveSequence => result.recursive += 1,
+                AnyWithin(_) | AnyExcept(_) | NumRange(..) => {
+                    result.classes += 1;
+                }
+                StartCapture(..) | EndCapture(..) => {}
+                ExtGlob(kind, ref alternatives, _) => {
+                    // the least specific alternative is what's guaranteed
+                    let alt = alternatives.iter()
+                        .map(|alt| Specificity::of(alt))
+                        .min()
+                        .unwrap_or_default();
+                    match kind {
+                        ExtGlobKind::ExactlyOne => result.add(&alt),
+                        ExtGlobKind::OneOrMore => {
+                            result.add(&alt);
+                            result.sequences += 1;
+                        }
+                        _ => result.sequences += 1,
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn add(&mut self, other: &Specificity) {
+        self.literals += other.literals;
+        self.recursive += other.recursive;
+        self.sequences += other.sequences;
+        self.any_chars += other.any_chars;
+        self.classes += other.classes;
+    }
+
+    /// Number of literal characters in the pattern
+    pub fn literals(&self) -> usize {
+        self.literals
+    }
+
+    /// Number of wildcards (of any kind) in the pattern
+    pub fn wildcards(&self) -> usize {
+        self.recursive + self.sequences + self.any_chars + self.classes
+    }
+
+    fn key(&self) -> (usize, Reverse<usize>, Reverse<usize>,
+                      Reverse<usize>, Reverse<usize>)
+    {
+        (self.literals, Reverse(self.recursive), Reverse(self.sequences),
+         Reverse(self.any_chars), Reverse(self.classes))
+    }
+}
+
+impl PartialOrd for Specificity {
+    fn partial_cmp(&self, other: &Specificity) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Specificity {
+    fn cmp(&self, other: &Specificity) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Return the most specific of the patterns matching the path, along with
+/// the captures, using the default match options
+///
+/// See `Specificity` for how patterns are ranked. If several matching
+/// patterns are equally specific, the first one wins.
+///
+/// # Examples
+///
+/// ```rust
+/// use capturing_glob::{Pattern, best_match};
+///
+/// let routes = [
+///     Pattern::new("(**)/(*).html").unwrap(),
+///     Pattern::new("blog/(*)/index.html").unwrap(),
+///     Pattern::new("blog/(*).html").unwrap(),
+/// ];
+/// let (pattern, entry) = best_match(&routes, "blog/hello.html").unwrap();
+/// assert_eq!(pattern.as_str(), "blog/(*).html");
+/// assert_eq!(entry.group(1).unwrap(), "hello");
+/// ```
+pub fn best_match<'a, I>(patterns: I, path: &str) -> Option<(&'a Pattern, Entry)>
+    where I: IntoIterator<Item=&'a Pattern>,
+{
+    best_match_with(patterns, path, &MatchOptions::new())
+}
+
+/// Return the most specific of the patterns matching the path, along with
+/// the captures, using the specified match options
+pub fn best_match_with<'a, I>(patterns: I, path: &str, options: &MatchOptions)
+    -> Option<(&'a Pattern, Entry)>
+    where I: IntoIterator<Item=&'a Pattern>,
+{
+    let mut best: Option<(Specificity, &Pattern, Entry)> = None;
+    for pattern in patterns {
+        let specificity = pattern.specificity();
+        if best.as_ref().is_some_and(|(s, _, _)| *s >= specificity) {
+            continue;
+        }
+        if let Some(entry) = pattern.captures_with(path, options) {
+            best = Some((specificity, pattern, entry));
+        }
+    }
+    best.map(|(_, pattern, entry)| (pattern, entry))
+}
+
+#[cfg(test)]
+mod test {
+    use Pattern;
+
+    fn spec(s: &str) -> super::Specificity {
+        Pattern::new(s).unwrap().specificity()
+    }
+
+    #[test]
+    fn ranking() {
+        let mut patterns = vec!["**/*.rs", "src/*", "src/lib.rs", "src/*.rs",
+                                "src/?ib.rs", "src/[lm]ib.rs", "src/**/*.rs"];
+        patterns.sort_by_key(|p| spec(p));
+        assert_eq!(patterns, ["**/*.rs", "src/*", "src/**/*.rs", "src/*.rs",
+                              "src/?ib.rs", "src/[lm]ib.rs", "src/lib.rs"]);
+    }
+
+    #[test]
+    fn alternatives() {
+        let braces = |s: &str| Pattern::new_with(s, &::PatternOptions {
+            braces: true,
+            .. ::PatternOptions::new()
+        }).unwrap().specificity();
+        assert_eq!(braces("{a,bc}x"), spec("ax"));
+        assert_eq!(spec("(x)"), spec("x"));
+        assert!(braces("{1..9}") < spec("9"));
+        let extglob = |s: &str| Pattern::new_with(s, &::PatternOptions {
+            extglob: true,
+            .. ::PatternOptions::new()
+        }).unwrap().specificity();
+        assert_eq!(extglob("@(ab|c)"), spec("c"));
+        assert_eq!(extglob("+(ab)"), spec("ab*"));
+        assert_eq!(extglob("!(ab)"), spec("*"));
+    }
+}
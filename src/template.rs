@@ -0,0 +1,313 @@
+use std::error::Error;
+use std::fmt;
+
+use {Entry, Pattern};
+
+
+/// A string with references to the capture groups, rendered from an
+/// `Entry`
+///
+/// Unlike `Pattern::substitute()`, the template may contain any
+/// characters. The references are:
+///
+/// * `$1` or `${1}` — capture group number one
+/// * `${name}` — the group named like `(?<name>...)` in the source pattern
+/// * `${1:upper}`, `${name:lower}` — the group converted to upper or lower
+///   case
+/// * `${1:pad=4}` — the group padded by zeros on the left up to 4
+///   characters, the width is at most 255
+/// * `${1:stem}` — the group with the last extension stripped, e.g.
+///   `a.tar` for `a.tar.gz`
+/// * `${1:noext}` — the group with all extensions stripped
+/// * `$$` — the dollar sign itself
+///
+/// Modifiers can be chained, e.g. `${1:stem:upper}`.
+///
+/// # Examples
+///
+/// ```rust
+/// use capturing_glob::{Pattern, Template};
+///
+/// let pattern = Pattern::new("content/(?<lang>*)/(*).md").unwrap();
+/// let template = Template::new("build/${lang}/${2:upper}.html", &pattern)
+///     .unwrap();
+/// let entry = pattern.captures("content/en/index.md").unwrap();
+/// assert_eq!(template.render(&entry), "build/en/INDEX.html");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Template {
+    original: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Part {
+    Literal(String),
+    Group(usize, Vec<Modifier>),
+}
+
+// Wider padding is most likely a typo, and would allocate a lot
+const MAX_PAD: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Modifier {
+    Upper,
+    Lower,
+    Pad(usize),
+    Stem,
+    NoExt,
+}
+
+/// A template parsing error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The template is malformed
+    Syntax {
+        /// The byte offset of the error in the template
+        pos: usize,
+        /// A message describing the error
+        msg: &'static str,
+    },
+    /// The source pattern has fewer capture groups
+    NoSuchGroup(usize),
+    /// The source pattern has no group with this name
+    NoSuchName(String),
+    /// The modifier is not one of the supported ones
+    UnknownModifier(String),
+}
+
+impl Template {
+    /// Parse the template, checking that the referenced groups exist in
+    /// the `source` pattern, i.e. the one entries are matched by
+    pub fn new(template: &str, source: &Pattern)
+        -> Result<Template, TemplateError>
+    {
+        let syntax = |pos, msg| TemplateError::Syntax { pos, msg };
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.char_indices().peekable();
+        while let Some((pos, c)) = chars.next() {
+            if c != '$' {
+                literal.push(c);
+                continue;
+            }
+            let (reference, modifiers) = match chars.peek().cloned() {
+                Some((_, '$')) => {
+                    chars.next();
+                    literal.push('$');
+                    continue;
+                }
+                Some((start, '{')) => {
+                    let end = template[start..].find('}')
+                        .ok_or(syntax(start, "unclosed `${`"))? + start;
+                    while chars.next_if(|&(i, _)| i <= end).is_some() {}
+                    let mut items = template[start+1..end].split(':');
+                    let reference = items.next().unwrap_or("");
+                    let modifiers = items.map(Modifier::parse)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    (reference, modifiers)
+                }
+                Some((start, c)) if c.is_ascii_digit() => {
+                    let mut end = start;
+                    while let Some((i, c)) = chars.next_if(|&(_, c)| {
+                        c.is_ascii_digit()
+                    }) {
+                        end = i + c.len_utf8();
+                    }
+                    (&template[start..end], Vec::new())
+                }
+                _ => return Err(syntax(pos, "expected group after `$`, \
+                                             use `$$` for the dollar sign")),
+            };
+            let group = if reference.is_empty() {
+                return Err(syntax(pos, "empty group reference"));
+            } else if let Ok(n) = reference.parse::<usize>() {
+                if n == 0 || n > source.group_count() {
+                    return Err(TemplateError::NoSuchGroup(n));
+                }
+                n
+            } else {
+                source.group_index(reference).ok_or_else(|| {
+                    TemplateError::NoSuchName(reference.to_string())
+                })?
+            };
+            if !literal.is_empty() {
+                parts.push(Part::Literal(literal.clone()));
+                literal.clear();
+            }
+            parts.push(Part::Group(group, modifiers));
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(Template {
+            original: template.to_string(),
+            parts,
+        })
+    }
+
+    /// Access the original template as a string
+    pub fn as_str(&self) -> &str {
+        &self.original
+    }
+
+    /// Render the template with the groups of the entry
+    ///
+    /// Groups that are not valid UTF-8 are converted lossily.
+    pub fn render(&self, entry: &Entry) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match *part {
+                Part::Literal(ref text) => result.push_str(text),
+                Part::Group(n, ref modifiers) => {
+                    let mut value = entry.group(n)
+                        .map(|g| g.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    for modifier in modifiers {
+                        value = modifier.apply(value);
+                    }
+                    result.push_str(&value);
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Modifier {
+    fn parse(text: &str) -> Result<Modifier, TemplateError> {
+        let unknown = || TemplateError::UnknownModifier(text.to_string());
+        match text {
+            "upper" => Ok(Modifier::Upper),
+            "lower" => Ok(Modifier::Lower),
+            "stem" => Ok(Modifier::Stem),
+            "noext" => Ok(Modifier::NoExt),
+            _ => match text.split_once('=') {
+                Some(("pad", width)) => match width.parse() {
+                    Ok(width) if width <= MAX_PAD => Ok(Modifier::Pad(width)),
+                    _ => Err(unknown()),
+                },
+                _ => Err(unknown()),
+            },
+        }
+    }
+
+    fn apply(&self, value: String) -> String {
+        // extensions are only looked for in the last path component, and
+        // a leading dot like in `.bashrc` doesn't start one
+        let name_start = value.rfind('/').map(|i| i + 1).unwrap_or(0);
+        let ext_start = |from_end| {
+            let name = &value[name_start..];
+            let dot = if from_end { name.rfind('.') } else {
+                name[1.min(name.len())..].find('.').map(|i| i + 1)
+            };
+            dot.filter(|&i| i > 0).map(|i| name_start + i)
+        };
+        match *self {
+            Modifier::Upper => value.to_uppercase(),
+            Modifier::Lower => value.to_lowercase(),
+            Modifier::Pad(width) => {
+                let len = value.chars().count();
+                let mut result = "0".repeat(width.saturating_sub(len));
+                result.push_str(&value);
+                result
+            }
+            Modifier::Stem => match ext_start(true) {
+                Some(i) => value[..i].to_string(),
+                None => value,
+            },
+            Modifier::NoExt => match ext_start(false) {
+                Some(i) => value[..i].to_string(),
+                None => value,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.original.fmt(f)
+    }
+}
+
+impl Error for TemplateError {}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TemplateError::Syntax { pos, msg } => {
+                write!(f, "Template syntax error near position {}: {}",
+                       pos, msg)
+            }
+            TemplateError::NoSuchGroup(n) => {
+                write!(f, "no capture group {} in the pattern", n)
+            }
+            TemplateError::NoSuchName(ref name) => {
+                write!(f, "no capture group named {:?} in the pattern", name)
+            }
+            TemplateError::UnknownModifier(ref modifier) => {
+                write!(f, "unknown template modifier {:?}", modifier)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use Pattern;
+    use super::{Template, TemplateError};
+
+    fn render(pattern: &str, template: &str, path: &str) -> String {
+        let pattern = Pattern::new(pattern).unwrap();
+        let template = Template::new(template, &pattern).unwrap();
+        template.render(&pattern.captures(path).unwrap())
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(render("(*)-(*)", "$2-$1 ${1}0 $$1", "a-b"), "b-a a0 $1");
+        assert_eq!(render("(?<x>*)", "[${x}](*)", "a"), "[a](*)");
+        assert_eq!(render("(*)/(*)/(*)/(*)/(*)/(*)/(*)/(*)/(*)/(*)",
+                          "$10${1}0", "a/b/c/d/e/f/g/h/i/j"), "ja0");
+    }
+
+    #[test]
+    fn modifiers() {
+        assert_eq!(render("(*)", "${1:upper}", "Ab"), "AB");
+        assert_eq!(render("(*)", "${1:lower}", "Ab"), "ab");
+        assert_eq!(render("(*)", "${1:pad=4}", "7"), "0007");
+        assert_eq!(render("(*)", "${1:pad=2}", "1234"), "1234");
+        assert_eq!(render("(*)", "${1:pad=255}", "7").len(), 255);
+        assert_eq!(render("(*)", "${1:stem}", "a.tar.gz"), "a.tar");
+        assert_eq!(render("(*)", "${1:noext}", "a.tar.gz"), "a");
+        assert_eq!(render("(*)", "${1:noext}", ".bashrc"), ".bashrc");
+        assert_eq!(render("(**/*)", "${1:stem}", "x.d/y"), "x.d/y");
+        assert_eq!(render("(*)", "${1:stem:upper}", "a.md"), "A");
+    }
+
+    #[test]
+    fn errors() {
+        let pattern = Pattern::new("(?<lang>*)/(*)").unwrap();
+        let error = |t| Template::new(t, &pattern).unwrap_err();
+        assert_eq!(error("$3"), TemplateError::NoSuchGroup(3));
+        assert_eq!(error("${0}"), TemplateError::NoSuchGroup(0));
+        assert_eq!(error("${page}"),
+                   TemplateError::NoSuchName("page".into()));
+        assert_eq!(error("${1:title}"),
+                   TemplateError::UnknownModifier("title".into()));
+        assert_eq!(error("${1:pad=x}"),
+                   TemplateError::UnknownModifier("pad=x".into()));
+        assert_eq!(error("${1:pad=256}"),
+                   TemplateError::UnknownModifier("pad=256".into()));
+        assert_eq!(error("${1:pad=99999999999999999999}"),
+                   TemplateError::UnknownModifier(
+                       "pad=99999999999999999999".into()));
+        assert_eq!(error("a/${1"),
+                   TemplateError::Syntax { pos: 3, msg: "unclosed `${`" });
+        match error("cost: $") {
+            TemplateError::Syntax { pos: 6, .. } => {}
+            e => panic!("unexpected {:?}", e),
+        }
+        assert!(Template::new("$lang", &pattern).is_err());
+    }
+}
@@ -0,0 +1,285 @@
+use std::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use {glob_with, Entries, Entry, GlobError, MatchOptions, PatternError};
+
+
+/// An error of converting a capture group to a typed value, see
+/// `Entry::parse()`
+#[derive(Debug)]
+pub struct CaptureError {
+    group: usize,
+    value: Option<OsString>,
+    error: Box<dyn Error + Send + Sync>,
+}
+
+/// A type that can be made from the capture groups of an entry
+///
+/// It's implemented for tuples of up to eight `FromStr` types, which are
+/// parsed from the groups in order starting from the first one.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::net::Ipv4Addr;
+/// use capturing_glob::Pattern;
+///
+/// let pattern = Pattern::new("hosts/(*)/port-(*)").unwrap();
+/// let entry = pattern.captures("hosts/10.0.0.1/port-8080").unwrap();
+/// let (host, port) = entry.extract::<(Ipv4Addr, u16)>().unwrap();
+/// assert_eq!(host, Ipv4Addr::new(10, 0, 0, 1));
+/// assert_eq!(port, 8080);
+/// ```
+pub trait FromCaptures: Sized {
+    /// Convert the capture groups of the entry
+    fn from_captures(entry: &Entry) -> Result<Self, CaptureError>;
+}
+
+/// A type made from the paths matching its own pattern
+///
+/// It's usually implemented with `#[derive(FromGlob)]` from the
+/// `capturing-glob-derive` crate, which checks the pattern at compile time
+/// and maps the fields to the capture groups by name (or by position for
+/// tuple structs).
+///
+/// # Examples
+///
+/// ```rust
+/// use capturing_glob::{Entry, CaptureError, FromGlob};
+///
+/// struct Module {
+///     name: String,
+/// }
+///
+/// impl FromGlob for Module {
+///     const PATTERN: &'static str = "src/(*).rs";
+///     fn from_entry(entry: &Entry) -> Result<Module, CaptureError> {
+///         Ok(Module { name: entry.parse(1)? })
+///     }
+/// }
+///
+/// for module in Module::glob().unwrap() {
+///     println!("mod {};", module.unwrap().name);
+/// }
+/// ```
+pub trait FromGlob: Sized {
+    /// The pattern the paths are matched by
+    const PATTERN: &'static str;
+
+    /// Convert the capture groups of a path matching `PATTERN`
+    fn from_entry(entry: &Entry) -> Result<Self, CaptureError>;
+
+    /// Return an iterator over the values made from the matching paths,
+    /// see `glob()`
+    fn glob() -> Result<GlobValues<Self>, PatternError> {
+        Self::glob_with(&MatchOptions::new())
+    }
+
+    /// Return an iterator over the values made from the matching paths
+    /// using the specified match options, see `glob_with()`
+    fn glob_with(options: &MatchOptions)
+        -> Result<GlobValues<Self>, PatternError>
+    {
+        Ok(GlobValues {
+            entries: glob_with(Self::PATTERN, options)?,
+            phantom: PhantomData,
+        })
+    }
+}
+
+/// An iterator over the values of a `FromGlob` type
+#[derive(Debug)]
+pub struct GlobValues<T> {
+    entries: Entries,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> GlobValues<T> {
+    /// Access the underlying walk, e.g. to set its options
+    pub fn entries(&mut self) -> &mut Entries {
+        &mut self.entries
+    }
+}
+
+impl<T: FromGlob> Iterator for GlobValues<T> {
+    type Item = Result<T, FromGlobError>;
+
+    fn next(&mut self) -> Option<Result<T, FromGlobError>> {
+        self.entries.next().map(|entry| {
+            let entry = entry.map_err(FromGlobError::Glob)?;
+            T::from_entry(&entry).map_err(|e| {
+                FromGlobError::Capture(entry.into(), e)
+            })
+        })
+    }
+}
+
+/// An error of `GlobValues`
+#[derive(Debug)]
+pub enum FromGlobError {
+    /// A directory can't be read
+    Glob(GlobError),
+    /// A capture group of the path can't be converted
+    Capture(PathBuf, CaptureError),
+}
+
+impl Entry {
+    /// Parse capture group number `n` (1-based) as any `FromStr` type, e.g.
+    /// a number, a date or a version
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use capturing_glob::Pattern;
+    ///
+    /// let pattern = Pattern::new("releases/(*)-(*).tar.gz").unwrap();
+    /// let mut entries = ["releases/app-10.tar.gz", "releases/app-9.tar.gz"]
+    ///     .iter()
+    ///     .map(|path| pattern.captures(path).unwrap())
+    ///     .collect::<Vec<_>>();
+    /// entries.sort_by_key(|e| e.parse::<u32>(2).ok());
+    /// assert_eq!(entries[0].group(2).unwrap(), "9");
+    ///
+    /// let error = entries[0].parse::<u32>(1).unwrap_err();
+    /// assert_eq!(error.group(), 1);
+    /// assert_eq!(error.value().unwrap(), "app");
+    /// ```
+    pub fn parse<T>(&self, n: usize) -> Result<T, CaptureError>
+        where T: FromStr,
+              T::Err: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let value = match self.group(n) {
+            Some(value) => value,
+            None => return Err(CaptureError {
+                group: n,
+                value: None,
+                error: "no such capture group".into(),
+            }),
+        };
+        let error = |error| CaptureError {
+            group: n,
+            value: Some(value.to_os_string()),
+            error,
+        };
+        let text = value.to_str()
+            .ok_or_else(|| error("capture group is not valid UTF-8".into()))?;
+        text.parse().map_err(|e: T::Err| error(e.into()))
+    }
+
+    /// Convert the capture groups to a typed value, see `FromCaptures`
+    pub fn extract<T: FromCaptures>(&self) -> Result<T, CaptureError> {
+        T::from_captures(self)
+    }
+}
+
+macro_rules! tuple_impl {
+    ($($name:ident = $n:expr),+) => {
+        impl<$($name),+> FromCaptures for ($($name,)+)
+            where $($name: FromStr,
+                    $name::Err: Into<Box<dyn Error + Send + Sync>>),+
+        {
+            fn from_captures(entry: &Entry) -> Result<Self, CaptureError> {
+                Ok(($(entry.parse::<$name>($n)?,)+))
+            }
+        }
+    }
+}
+
+tuple_impl!(A = 1);
+tuple_impl!(A = 1, B = 2);
+tuple_impl!(A = 1, B = 2, C = 3);
+tuple_impl!(A = 1, B = 2, C = 3, D = 4);
+tuple_impl!(A = 1, B = 2, C = 3, D = 4, E = 5);
+tuple_impl!(A = 1, B = 2, C = 3, D = 4, E = 5, F = 6);
+tuple_impl!(A = 1, B = 2, C = 3, D = 4, E = 5, F = 6, G = 7);
+tuple_impl!(A = 1, B = 2, C = 3, D = 4, E = 5, F = 6, G = 7, H = 8);
+
+impl CaptureError {
+    /// Number of the capture group that failed to convert
+    pub fn group(&self) -> usize {
+        self.group
+    }
+    /// The value of the group, `None` if the pattern has no such group
+    pub fn value(&self) -> Option<&OsStr> {
+        self.value.as_ref().map(|v| &v[..])
+    }
+}
+
+impl Error for CaptureError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&*self.error)
+    }
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.value {
+            Some(ref value) => write!(f, "invalid capture group {} {:?}: {}",
+                                      self.group, value, self.error),
+            None => write!(f, "invalid capture group {}: {}",
+                           self.group, self.error),
+        }
+    }
+}
+
+impl Error for FromGlobError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            FromGlobError::Glob(ref e) => Some(e),
+            FromGlobError::Capture(_, ref e) => Some(e),
+        }
+    }
+}
+
+impl fmt::Display for FromGlobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromGlobError::Glob(ref e) => e.fmt(f),
+            FromGlobError::Capture(ref path, ref e) => {
+                write!(f, "{} of `{}`", e, path.display())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::error::Error;
+    use Pattern;
+
+    #[test]
+    fn parse() {
+        let pattern = Pattern::new("v(*).(*)/(*)").unwrap();
+        let entry = pattern.captures("v1.x/2018-01-02").unwrap();
+        assert_eq!(entry.parse::<u8>(1).unwrap(), 1);
+        assert_eq!(entry.parse::<String>(3).unwrap(), "2018-01-02");
+
+        let error = entry.parse::<u8>(2).unwrap_err();
+        assert_eq!(error.group(), 2);
+        assert_eq!(error.value().unwrap(), "x");
+        assert_eq!(error.to_string(),
+                   "invalid capture group 2 \"x\": \
+                    invalid digit found in string");
+        assert!(error.source().is_some());
+
+        let error = entry.parse::<u8>(4).unwrap_err();
+        assert_eq!(error.group(), 4);
+        assert!(error.value().is_none());
+    }
+
+    #[test]
+    fn extract() {
+        let pattern = Pattern::new("(*)-(*)-(*)").unwrap();
+        let entry = pattern.captures("a-2-3.5").unwrap();
+        let (a, b, c) = entry.extract::<(char, u32, f64)>().unwrap();
+        assert_eq!((a, b, c), ('a', 2, 3.5));
+        assert_eq!(entry.extract::<(String,)>().unwrap().0, "a");
+        assert_eq!(entry.extract::<(u32, u32)>().unwrap_err().group(), 1);
+        assert_eq!(entry.extract::<(String, u32, f64, u8)>()
+                   .unwrap_err().group(), 4);
+    }
+}
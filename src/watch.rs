@@ -0,0 +1,276 @@
+//! Watching for the paths matching a pattern, enabled by the `watch`
+//! feature (Linux only)
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+
+use {Entry, MatchOptions, Pattern};
+
+
+/// A change of a path matching the watched pattern
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// The path was created, moved into the watched tree, or existed when
+    /// the watch was started
+    Created(Entry),
+    /// The contents of the file were written to
+    Modified(Entry),
+    /// The path was removed, or moved out of the watched tree
+    Removed(Entry),
+}
+
+impl WatchEvent {
+    /// The path along with the capture groups
+    pub fn entry(&self) -> &Entry {
+        match *self {
+            WatchEvent::Created(ref e) => e,
+            WatchEvent::Modified(ref e) => e,
+            WatchEvent::Removed(ref e) => e,
+        }
+    }
+}
+
+/// An iterator over the changes of the paths matching a pattern, see
+/// `watch()`
+///
+/// The iterator blocks until there are changes and never ends, use
+/// `try_next()` to poll for the changes instead.
+#[derive(Debug)]
+pub struct Watcher {
+    inotify: Inotify,
+    pattern: Pattern,
+    options: MatchOptions,
+    root: PathBuf,
+    // max number of components of the paths matched, `None` for `**`
+    max_depth: Option<usize>,
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    known: BTreeSet<PathBuf>,
+    queue: VecDeque<WatchEvent>,
+    buffer: Vec<u8>,
+}
+
+/// Start watching for the paths matching the pattern
+///
+/// Only the directory of the literal prefix of the pattern (see
+/// `Pattern::literal_prefix()`) and its subdirectories that can contain
+/// matching paths are watched. For patterns containing `**` directories are
+/// watched as they appear. Symbolic links to directories are not followed.
+///
+/// The paths existing when the watch is started are reported as `Created`
+/// first, so a daemon can process them along with the new ones. Matching is
+/// done like in `glob_with()`, i.e. `require_literal_separator` is always
+/// set. Writing a new file produces both `Created` and `Modified` events.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use capturing_glob::{watch, MatchOptions, Pattern, WatchEvent};
+///
+/// let pattern = Pattern::new("incoming/(*)/(*).csv").unwrap();
+/// for event in watch(&pattern, &MatchOptions::new()).unwrap() {
+///     match event.unwrap() {
+///         WatchEvent::Created(e) | WatchEvent::Modified(e) => {
+///             println!("load {:?} into {:?}", e.path(), e.group(1).unwrap());
+///         }
+///         WatchEvent::Removed(e) => println!("gone {:?}", e.path()),
+///     }
+/// }
+/// ```
+pub fn watch(pattern: &Pattern, options: &MatchOptions) -> io::Result<Watcher>
+{
+    let mut watcher = Watcher {
+        inotify: Inotify::init()?,
+        pattern: pattern.clone(),
+        options: MatchOptions {
+            require_literal_separator: true,
+            .. options.clone()
+        },
+        root: pattern.literal_prefix(),
+        max_depth: pattern.depth().1,
+        dirs: HashMap::new(),
+        known: BTreeSet::new(),
+        queue: VecDeque::new(),
+        buffer: vec![0; 4096],
+    };
+    let root = watcher.root.clone();
+    watcher.add_watch(&root)?;
+    watcher.scan_dir(&root);
+    Ok(watcher)
+}
+
+impl Watcher {
+    /// Return the next change if there is one already, without blocking
+    pub fn try_next(&mut self) -> io::Result<Option<WatchEvent>> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Ok(Some(event));
+            }
+            let events = match self.inotify.read_events(&mut self.buffer) {
+                Ok(events) => events.map(owned).collect::<Vec<_>>(),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    return Ok(None);
+                }
+                Err(e) => return Err(e),
+            };
+            for (wd, mask, name) in events {
+                self.handle(wd, mask, name);
+            }
+        }
+    }
+
+    fn matches(&self, path: &Path) -> Option<Entry> {
+        self.pattern.captures_path_with(path, &self.options)
+    }
+
+    // Whether the directory can contain matching paths
+    fn worth_watching(&self, dir: &Path) -> bool {
+        let depth = dir.components()
+            .filter(|c| !matches!(c, Component::RootDir | Component::Prefix(_)))
+            .count();
+        self.max_depth.map_or(true, |max| depth < max)
+    }
+
+    fn add_watch(&mut self, dir: &Path) -> io::Result<()> {
+        let real = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MODIFY
+            | WatchMask::MOVED_FROM | WatchMask::MOVED_TO
+            | WatchMask::ONLYDIR | WatchMask::DONT_FOLLOW;
+        let wd = self.inotify.watches().add(real, mask)?;
+        self.dirs.insert(wd, dir.to_path_buf());
+        Ok(())
+    }
+
+    // Reports the matching paths in the directory that aren't known yet,
+    // the directory is already watched
+    fn scan_dir(&mut self, dir: &Path) {
+        let real = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+        let names = match fs::read_dir(real) {
+            Ok(entries) => entries.filter_map(|e| e.ok())
+                .map(|e| e.file_name())
+                .collect::<Vec<_>>(),
+            // the directory has vanished, its removal is reported anyway
+            Err(_) => return,
+        };
+        for name in names {
+            let path = dir.join(name);
+            self.found(&path);
+        }
+    }
+
+    fn found(&mut self, path: &Path) {
+        if !self.known.contains(path) {
+            if let Some(entry) = self.matches(path) {
+                self.known.insert(path.to_path_buf());
+                self.queue.push_back(WatchEvent::Created(entry));
+            }
+        }
+        let is_dir = fs::symlink_metadata(path)
+            .map(|m| m.is_dir()).unwrap_or(false);
+        if is_dir && self.worth_watching(path) && self.add_watch(path).is_ok()
+        {
+            self.scan_dir(path);
+        }
+    }
+
+    fn removed(&mut self, path: &Path) {
+        let gone = self.known.iter()
+            .filter(|p| p.starts_with(path))
+            .cloned()
+            .collect::<Vec<_>>();
+        for p in gone {
+            self.known.remove(&p);
+            if let Some(entry) = self.matches(&p) {
+                self.queue.push_back(WatchEvent::Removed(entry));
+            }
+        }
+        // a moved directory is still watched at the new location
+        let watched = self.dirs.iter()
+            .filter(|&(_, dir)| dir.starts_with(path))
+            .map(|(wd, _)| wd.clone())
+            .collect::<Vec<_>>();
+        for wd in watched {
+            self.dirs.remove(&wd);
+            self.inotify.watches().remove(wd).ok();
+        }
+    }
+
+    fn modified(&mut self, path: &Path) {
+        if !self.known.contains(path) {
+            return;
+        }
+        // a single write is often reported several times
+        if let Some(WatchEvent::Modified(last)) = self.queue.back() {
+            if last.path() == path {
+                return;
+            }
+        }
+        if let Some(entry) = self.matches(path) {
+            self.queue.push_back(WatchEvent::Modified(entry));
+        }
+    }
+
+    fn handle(&mut self, wd: WatchDescriptor, mask: EventMask,
+              name: Option<OsString>)
+    {
+        if mask.contains(EventMask::Q_OVERFLOW) {
+            // events are lost, compare the tree with what we know instead
+            let root = self.root.clone();
+            self.scan_dir(&root);
+            let gone = self.known.iter()
+                .filter(|p| fs::symlink_metadata(p).is_err())
+                .cloned()
+                .collect::<Vec<_>>();
+            for path in gone {
+                self.removed(&path);
+            }
+            return;
+        }
+        if mask.contains(EventMask::IGNORED) {
+            self.dirs.remove(&wd);
+            return;
+        }
+        let path = match (self.dirs.get(&wd), name) {
+            (Some(dir), Some(name)) => dir.join(name),
+            _ => return,
+        };
+        if mask.intersects(EventMask::CREATE | EventMask::MOVED_TO) {
+            self.found(&path);
+        } else if mask.contains(EventMask::MODIFY) {
+            self.modified(&path);
+        } else if mask.intersects(EventMask::DELETE | EventMask::MOVED_FROM) {
+            self.removed(&path);
+        }
+    }
+}
+
+impl Iterator for Watcher {
+    type Item = io::Result<WatchEvent>;
+
+    fn next(&mut self) -> Option<io::Result<WatchEvent>> {
+        loop {
+            match self.try_next() {
+                Ok(Some(event)) => return Some(Ok(event)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+            let events = match self.inotify.read_events_blocking(&mut self.buffer) {
+                Ok(events) => events.map(owned).collect::<Vec<_>>(),
+                Err(e) => return Some(Err(e)),
+            };
+            for (wd, mask, name) in events {
+                self.handle(wd, mask, name);
+            }
+        }
+    }
+}
+
+fn owned(event: ::inotify::Event<&::std::ffi::OsStr>)
+    -> (WatchDescriptor, EventMask, Option<OsString>)
+{
+    (event.wd, event.mask, event.name.map(|n| n.to_os_string()))
+}
@@ -0,0 +1,121 @@
+#![cfg(any(feature = "tar", feature = "zip"))]
+
+extern crate capturing_glob as glob;
+extern crate tempdir;
+#[cfg(feature = "tar")] extern crate tar;
+#[cfg(feature = "tar")] extern crate flate2;
+#[cfg(feature = "zip")] extern crate zip;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use glob::{glob_in, FileSystem, MatchOptions};
+use tempdir::TempDir;
+
+const MEMBERS: &[(&str, &str)] = &[
+    ("app-1.0/bin/app", "#!/bin/app"),
+    ("app-1.0/bin/helper", "helper"),
+    ("app-1.0/share/README", "read me"),
+    ("tools/bin/fmt", "fmt"),
+];
+
+fn read<R: Read>(mut reader: R) -> String {
+    let mut result = String::new();
+    reader.read_to_string(&mut result).unwrap();
+    result
+}
+
+fn check<F, O, R>(archive: &F, open: O)
+    where F: FileSystem,
+          O: Fn(&F, &Path) -> R,
+          R: Read,
+{
+    let options = MatchOptions::new();
+    let found = glob_in(archive, "(*)/bin/(*)", &options).unwrap()
+        .map(|e| {
+            let e = e.unwrap();
+            (e.group(1).unwrap().to_str().unwrap().to_owned(),
+             e.group(2).unwrap().to_str().unwrap().to_owned(),
+             read(open(archive, e.path())))
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(found, [
+        ("app-1.0".to_owned(), "app".to_owned(), "#!/bin/app".to_owned()),
+        ("app-1.0".to_owned(), "helper".to_owned(), "helper".to_owned()),
+        ("tools".to_owned(), "fmt".to_owned(), "fmt".to_owned()),
+    ]);
+    let dirs = glob_in(archive, "*/", &options).unwrap()
+        .map(|e| e.unwrap().path().to_path_buf())
+        .collect::<Vec<_>>();
+    assert_eq!(dirs, [Path::new("app-1.0"), Path::new("tools")]);
+}
+
+#[cfg(feature = "tar")]
+fn build_tar<W: Write>(writer: W) {
+    let mut builder = tar::Builder::new(writer);
+    builder.append_dir("app-1.0", ".").unwrap();
+    for &(path, data) in MEMBERS {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, path, data.as_bytes()).unwrap();
+    }
+    // the builder refuses to write these names
+    for name in &["../escape", "/etc/escape"] {
+        let mut header = tar::Header::new_gnu();
+        header.as_old_mut().name[..name.len()]
+            .copy_from_slice(name.as_bytes());
+        header.set_size(6);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append(&header, &b"escape"[..]).unwrap();
+    }
+    builder.into_inner().unwrap();
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn tar_archive() {
+    use glob::TarArchive;
+
+    let dir = TempDir::new("glob-archive").unwrap();
+    let plain = dir.path().join("release.tar");
+    build_tar(File::create(&plain).unwrap());
+    let gzipped = dir.path().join("release.tar.gz");
+    let encoder = flate2::write::GzEncoder::new(
+        File::create(&gzipped).unwrap(), flate2::Compression::default());
+    build_tar(encoder);
+
+    for path in &[plain, gzipped] {
+        let archive = TarArchive::open(path).unwrap();
+        check(&archive, |a, p| a.open_member(p).unwrap());
+        assert!(archive.open_member(Path::new("app-1.0")).is_err());
+        assert!(!archive.exists(Path::new("../escape")));
+        assert!(!archive.exists(Path::new("/etc/escape")));
+        assert!(archive.open_member(Path::new("/etc/escape")).is_err());
+    }
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn zip_archive() {
+    use glob::ZipArchive;
+
+    let dir = TempDir::new("glob-archive").unwrap();
+    let path = dir.path().join("release.zip");
+    let mut writer = zip::ZipWriter::new(File::create(&path).unwrap());
+    let options = zip::write::FileOptions::default();
+    writer.add_directory("app-1.0/", options).unwrap();
+    for &(name, data) in MEMBERS {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(data.as_bytes()).unwrap();
+    }
+    writer.start_file("../escape", options).unwrap();
+    writer.finish().unwrap();
+
+    let archive = ZipArchive::open(&path).unwrap();
+    check(&archive, |a, p| a.open_member(p).unwrap());
+    assert!(!archive.exists(Path::new("../escape")));
+}
@@ -0,0 +1,135 @@
+extern crate tempdir;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use tempdir::TempDir;
+
+fn tree() -> TempDir {
+    let root = TempDir::new("cglob").unwrap();
+    for file in &["src/lib.rs", "src/bin/tool.rs", "src/README.md"] {
+        let path = root.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "").unwrap();
+    }
+    root
+}
+
+fn cglob(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cglob"))
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn paths() {
+    let root = tree();
+    let output = cglob(root.path(), &["src/**/*.rs"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "src/bin/tool.rs\nsrc/lib.rs\n");
+
+    let output = cglob(root.path(), &["-0", "src/*.rs"]);
+    assert_eq!(stdout(&output), "src/lib.rs\0");
+
+    let output = cglob(root.path(), &["-i", "src/readme.*"]);
+    assert_eq!(stdout(&output), "src/README.md\n");
+
+    let output = cglob(root.path(), &["--braces", "src/*.{rs,md}"]);
+    assert_eq!(stdout(&output), "src/README.md\nsrc/lib.rs\n");
+    let output = cglob(root.path(), &["src/*.{rs,md}"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn formats() {
+    let root = tree();
+    let output = cglob(root.path(), &["--tsv", "src/(*)/(*).rs"]);
+    assert_eq!(stdout(&output), "src/bin/tool.rs\tbin\ttool\n");
+
+    let output = cglob(root.path(), &["--tsv", "src/(**)/(*).rs"]);
+    assert_eq!(stdout(&output),
+               "src/bin/tool.rs\tbin\ttool\nsrc/lib.rs\t\tlib\n");
+
+    let output = cglob(root.path(), &["--json", "src/(?<dir>*)/(*).rs"]);
+    assert_eq!(stdout(&output), concat!(
+        r#"{"path":"src/bin/tool.rs","groups":["bin","tool"],"#,
+        r#""names":{"dir":"bin"}}"#, "\n"));
+}
+
+#[test]
+fn templates() {
+    let root = tree();
+    let output = cglob(root.path(), &["src/(*)/(*).rs", "--to", "out/$2-$1.o"]);
+    assert_eq!(stdout(&output), "out/tool-bin.o\n");
+
+    let output = cglob(root.path(), &["src/(*).rs", "--to", "${1:upper}.O"]);
+    assert_eq!(stdout(&output), "LIB.O\n");
+
+    let output = cglob(root.path(),
+        &["--tsv", "src/(?<dir>*)/(*).rs", "--to=${dir}/${2}[$$].o"]);
+    assert_eq!(stdout(&output), "src/bin/tool.rs\tbin\ttool\tbin/tool[$].o\n");
+
+    let output = cglob(root.path(), &["src/(*).rs", "--to", "out/(*).o"]);
+    assert_eq!(stdout(&output), "out/lib.o\n");
+
+    let output = cglob(root.path(), &["src/(*).rs", "--to", "out/$2.o"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn exit_codes() {
+    let root = tree();
+    assert_eq!(cglob(root.path(), &["*.txt"]).status.code(), Some(1));
+    assert_eq!(cglob(root.path(), &["src/[a"]).status.code(), Some(2));
+    assert_eq!(cglob(root.path(), &[]).status.code(), Some(2));
+    assert_eq!(cglob(root.path(), &["--bogus", "*"]).status.code(), Some(2));
+    assert_eq!(cglob(root.path(), &["--help"]).status.code(), Some(0));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_arguments() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = tree();
+    let output = Command::new(env!("CARGO_BIN_EXE_cglob"))
+        .arg(OsStr::from_bytes(b"src/\xff*"))
+        .current_dir(root.path())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr)
+            .contains("is not valid UTF-8"));
+}
+
+#[test]
+fn rename() {
+    let root = tree();
+    let output = cglob(root.path(),
+        &["--rename", "-n", "src/(*).rs", "--to", "src/$1_old.rs"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "src/lib.rs\tsrc/lib_old.rs\n");
+    assert!(root.path().join("src/lib.rs").exists());
+
+    let output = cglob(root.path(),
+        &["--rename", "src/(*)/(*).rs", "--to", "$2/$1.rs"]);
+    assert_eq!(stdout(&output), "src/bin/tool.rs\ttool/bin.rs\n");
+    assert!(root.path().join("tool/bin.rs").exists());
+    assert!(!root.path().join("src/bin/tool.rs").exists());
+
+    fs::write(root.path().join("src/main.rs"), "").unwrap();
+    let output = cglob(root.path(),
+        &["--rename", "src/(*).rs", "--to", "src/lib.rs"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(root.path().join("src/main.rs").exists());
+    assert_eq!(cglob(root.path(), &["--rename", "*"]).status.code(), Some(2));
+}
@@ -0,0 +1,39 @@
+extern crate capturing_glob as glob;
+extern crate tempdir;
+
+use std::fs;
+use std::path::Path;
+
+use glob::{glob_with_options, MatchOptions, PatternOptions};
+use tempdir::TempDir;
+
+#[test]
+fn glob_extglob_components() {
+    let root = TempDir::new("glob-extglob").unwrap();
+    for dir in &["src", "skip", "tests"] {
+        fs::create_dir(root.path().join(dir)).unwrap();
+        for file in &["a.rs", "b.txt"] {
+            fs::File::create(root.path().join(dir).join(file)).unwrap();
+        }
+    }
+
+    let syntax = PatternOptions {
+        extglob: true,
+        .. PatternOptions::new()
+    };
+    let pattern = format!("{}/!(skip)/(*).@(rs|md)", root.path().display());
+    let found = glob_with_options(&pattern, &syntax, &MatchOptions::new())
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect::<Vec<_>>();
+    let paths = found.iter()
+        .map(|e| e.path().strip_prefix(root.path()).unwrap().to_path_buf())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec![
+        Path::new("src/a.rs").to_path_buf(),
+        Path::new("tests/a.rs").to_path_buf(),
+    ]);
+    assert_eq!(found[0].group(1).unwrap(), "src");
+    assert_eq!(found[0].group(2).unwrap(), "a");
+    assert_eq!(found[1].group(3).unwrap(), "rs");
+}
@@ -0,0 +1,181 @@
+extern crate capturing_glob as glob;
+extern crate tempdir;
+
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::{glob_with, glob_in, FileSystem, MemoryFs, MatchOptions};
+use tempdir::TempDir;
+
+fn collect<I: Iterator<Item=glob::GlobResult>>(iter: I) -> Vec<PathBuf> {
+    iter.map(|e| e.unwrap().path().to_path_buf()).collect()
+}
+
+#[test]
+fn memory_fs_agrees_with_std() {
+    let root = TempDir::new("glob-fs").unwrap();
+    let mut memory = MemoryFs::new();
+    let files = ["a/x.rs", "a/b/y.rs", "a/b/c/z.md", "a/.hidden/w.rs",
+                 "d/x.rs", "e.rs"];
+    for file in &files {
+        let path = root.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::File::create(&path).unwrap();
+        memory.add_file(&path);
+    }
+    fs::create_dir(root.path().join("empty")).unwrap();
+    memory.add_dir(root.path().join("empty"));
+
+    let options = MatchOptions::new();
+    let patterns = ["*", "*/", "**", "**/*.rs", "a/**/(*).rs", "a/b/*",
+                    "a/*/../x.rs", "./a/b/c/z.md", "a/b/missing", "*/x.rs",
+                    "a/**/", "e.rs/*", "a/.*/*"];
+    for pattern in &patterns {
+        let pattern = format!("{}/{}", root.path().display(), pattern);
+        let expected = collect(glob_with(&pattern, &options).unwrap());
+        let found = collect(glob_in(&memory, &pattern, &options).unwrap());
+        assert_eq!(found, expected, "pattern {:?}", pattern);
+    }
+}
+
+struct Broken;
+
+impl FileSystem for Broken {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        if path.ends_with("secret") {
+            Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+        } else {
+            Ok(vec![Ok("secret".into()), Ok("public".into())])
+        }
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        path.components().count() < 3
+    }
+    fn exists(&self, path: &Path) -> bool {
+        path.components().count() < 4
+    }
+}
+
+#[test]
+fn custom_fs_errors() {
+    let results = glob_in(Broken, "*/*", &MatchOptions::new()).unwrap()
+        .collect::<Vec<_>>();
+    let paths = results.iter()
+        .map(|r| match *r {
+            Ok(ref entry) => entry.path().to_path_buf(),
+            Err(ref e) => e.path().to_path_buf(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(paths, [
+        Path::new("public/public"),
+        Path::new("public/secret"),
+        Path::new("secret"),
+    ]);
+    assert!(results[2].is_err());
+}
+
+// `a` can't be listed, and one entry of the current directory is broken
+struct Flaky;
+
+impl FileSystem for Flaky {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        let broken = || io::Error::new(io::ErrorKind::InvalidData, "broken");
+        match path.to_str().unwrap() {
+            "." => Ok(vec![Ok("b".into()), Err(broken()), Ok("a".into())]),
+            "a" => Err(io::Error::new(io::ErrorKind::PermissionDenied, "no")),
+            _ => Ok(vec![Ok("x".into())]),
+        }
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        path.components().count() < 2
+    }
+    fn exists(&self, _: &Path) -> bool {
+        true
+    }
+}
+
+fn describe(result: glob::GlobResult) -> String {
+    match result {
+        Ok(entry) => entry.path().display().to_string(),
+        Err(e) => format!("error {}: {}", e.path().display(), e.error()),
+    }
+}
+
+#[test]
+fn error_policy() {
+    use glob::ErrorPolicy::*;
+
+    let walk = |policy| {
+        glob_in(Flaky, "*/*", &MatchOptions::new()).unwrap()
+            .error_policy(policy)
+    };
+    let all = walk(Yield).map(describe).collect::<Vec<_>>();
+    assert_eq!(all, ["error .: broken", "error a: no", "b/x"]);
+    let skip = walk(Skip).map(describe).collect::<Vec<_>>();
+    assert_eq!(skip, ["b/x"]);
+    let abort = walk(Abort).map(describe).collect::<Vec<_>>();
+    assert_eq!(abort, ["error .: broken"]);
+
+    let mut entries = walk(Collect);
+    let found = entries.by_ref().map(describe).collect::<Vec<_>>();
+    assert_eq!(found, ["b/x"]);
+    assert_eq!(entries.errors().len(), 2);
+    assert_eq!(entries.errors()[1].path(), Path::new("a"));
+    assert_eq!(entries.take_errors().len(), 2);
+    assert!(entries.errors().is_empty());
+}
+
+#[test]
+fn error_kinds() {
+    use std::error::Error;
+    use glob::GlobErrorKind;
+
+    let errors = glob_in(Flaky, "*/x*", &MatchOptions::new()).unwrap()
+        .filter_map(|r| r.err())
+        .collect::<Vec<_>>();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].kind(), GlobErrorKind::Other);
+    assert_eq!(errors[0].depth(), 0);
+    assert_eq!(errors[0].component(), "*");
+    assert_eq!(errors[1].kind(), GlobErrorKind::PermissionDenied);
+    assert_eq!(errors[1].depth(), 1);
+    assert_eq!(errors[1].component(), "x*");
+    assert_eq!(errors[1].source().unwrap().to_string(), "no");
+}
+
+// Lists a directory that can't be read anymore
+struct Racy;
+
+impl FileSystem for Racy {
+    fn read_dir(&self, path: &Path) -> io::Result<Vec<io::Result<OsString>>> {
+        match path.to_str().unwrap() {
+            "." => Ok(vec![Ok("gone".into())]),
+            _ => Err(io::Error::new(io::ErrorKind::NotFound, "not found")),
+        }
+    }
+    fn is_dir(&self, _: &Path) -> bool {
+        true
+    }
+    fn exists(&self, _: &Path) -> bool {
+        true
+    }
+}
+
+#[test]
+fn vanished() {
+    use glob::GlobErrorKind;
+
+    let kinds = |pattern| {
+        glob_in(Racy, pattern, &MatchOptions::new()).unwrap()
+            .filter_map(|r| r.err())
+            .map(|e| e.kind())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(kinds("*/*"), [GlobErrorKind::Vanished]);
+    assert_eq!(kinds("**/*"), [GlobErrorKind::Vanished]);
+    assert_eq!(kinds("**/x"), [GlobErrorKind::Vanished]);
+    // the walk starts from the root, it was never seen to exist
+    assert_eq!(kinds("/*"), [GlobErrorKind::Other]);
+}
@@ -11,6 +11,7 @@
 // ignore-windows TempDir may cause IoError on windows: #10462
 
 #![cfg_attr(test, deny(warnings))]
+#![allow(clippy::ok_expect)]
 
 extern crate capturing_glob as glob;
 extern crate tempdir;
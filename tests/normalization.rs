@@ -0,0 +1,34 @@
+extern crate capturing_glob as glob;
+extern crate tempdir;
+
+use std::fs;
+
+use glob::{glob_with, MatchOptions};
+use tempdir::TempDir;
+
+#[test]
+fn glob_decomposed_names() {
+    let root = TempDir::new("glob-normalization").unwrap();
+    // names as they come from macOS (NFD)
+    fs::create_dir(root.path().join("cafe\u{301}")).unwrap();
+    fs::File::create(root.path().join("cafe\u{301}/cre\u{300}me.txt")).unwrap();
+
+    let options = MatchOptions {
+        normalization_insensitive: true,
+        .. MatchOptions::new()
+    };
+    // patterns as typed by users (NFC)
+    let pattern = format!("{}/caf\u{e9}/(*).txt", root.path().display());
+
+    let found = glob_with(&pattern, &MatchOptions::new()).unwrap()
+        .collect::<Vec<_>>();
+    assert!(found.is_empty());
+
+    let found = glob_with(&pattern, &options).unwrap()
+        .map(|e| e.unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].path(),
+               root.path().join("cafe\u{301}/cre\u{300}me.txt"));
+    assert_eq!(found[0].group(1).unwrap(), "cre\u{300}me");
+}
@@ -0,0 +1,61 @@
+extern crate capturing_glob as glob;
+
+use std::sync::{Arc, Mutex};
+
+use glob::{glob_in, CancelToken, MatchOptions, MemoryFs, Progress};
+
+fn tree() -> MemoryFs {
+    let mut fs = MemoryFs::new();
+    for dir in &["a", "b", "c"] {
+        for file in &["x.rs", "y.rs", "z.md"] {
+            fs.add_file(format!("src/{}/{}", dir, file));
+        }
+    }
+    fs
+}
+
+#[test]
+fn progress() {
+    let fs = tree();
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let copy = reports.clone();
+    let mut entries = glob_in(&fs, "src/*/*.rs", &MatchOptions::new()).unwrap()
+        .on_progress(move |p| copy.lock().unwrap().push(*p));
+    assert_eq!(entries.by_ref().count(), 6);
+    assert_eq!(*entries.progress(), Progress {
+        dirs_read: 4,
+        entries_examined: 12,
+        matches: 6,
+    });
+    let reports = reports.lock().unwrap();
+    let last = reports.last().unwrap();
+    assert_eq!((last.dirs_read, last.matches), (4, 6));
+    let mut matches = reports.iter().map(|p| p.matches).collect::<Vec<_>>();
+    matches.dedup();
+    assert_eq!(matches, [0, 1, 2, 3, 4, 5, 6]);
+    assert!(reports.windows(2).all(|w| w[0].dirs_read <= w[1].dirs_read));
+}
+
+#[test]
+fn cancel() {
+    let fs = tree();
+    let token = CancelToken::new();
+    let copy = token.clone();
+    let mut entries = glob_in(&fs, "src/**/*", &MatchOptions::new()).unwrap()
+        .cancel_token(token.clone())
+        .on_progress(move |p| if p.matches == 2 { copy.cancel() });
+    assert!(entries.next().unwrap().is_ok());
+    assert!(entries.next().unwrap().is_ok());
+    let err = entries.next().unwrap().unwrap_err();
+    assert!(err.is_cancelled());
+    assert_eq!(err.kind(), glob::GlobErrorKind::Cancelled);
+    assert!(::std::error::Error::source(&err).is_none());
+    assert!(entries.next().is_none());
+    assert!(token.is_cancelled());
+
+    let error = glob_in(&fs, "*", &MatchOptions::new()).unwrap()
+        .cancel_token(token)
+        .collect::<Vec<_>>();
+    assert_eq!(error.len(), 1);
+    assert!(error[0].as_ref().unwrap_err().is_cancelled());
+}
@@ -0,0 +1,36 @@
+extern crate capturing_glob as glob;
+extern crate tempdir;
+
+use std::fs;
+use std::path::Path;
+
+use glob::{glob_with_options, MatchOptions, PatternOptions};
+use tempdir::TempDir;
+
+#[test]
+fn glob_num_range() {
+    let root = TempDir::new("glob-ranges").unwrap();
+    for dir in &["shard-00", "shard-07", "shard-63", "shard-64", "shard-7"] {
+        fs::create_dir(root.path().join(dir)).unwrap();
+        fs::File::create(root.path().join(dir).join("a.bin")).unwrap();
+    }
+
+    let pattern = format!("{}/shard-({{00..63}})/*.bin", root.path().display());
+    let syntax = PatternOptions {
+        braces: true,
+        .. PatternOptions::new()
+    };
+    let found = glob_with_options(&pattern, &syntax, &MatchOptions::new())
+        .unwrap()
+        .map(|e| e.unwrap())
+        .collect::<Vec<_>>();
+    let paths = found.iter()
+        .map(|e| e.path().strip_prefix(root.path()).unwrap().to_path_buf())
+        .collect::<Vec<_>>();
+    assert_eq!(paths, vec![
+        Path::new("shard-00/a.bin").to_path_buf(),
+        Path::new("shard-07/a.bin").to_path_buf(),
+        Path::new("shard-63/a.bin").to_path_buf(),
+    ]);
+    assert_eq!(found[1].group(1).unwrap(), "07");
+}
@@ -0,0 +1,129 @@
+extern crate capturing_glob as glob;
+extern crate tempdir;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{plan_rename, MatchOptions, Rename, RenameError, RenamePlan};
+use tempdir::TempDir;
+
+fn rename(root: &Path, from: &str, to: &str) -> Rename {
+    Rename { from: root.join(from), to: root.join(to) }
+}
+
+fn read(path: PathBuf) -> String {
+    fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn glob_and_apply() {
+    let root = TempDir::new("glob-rename").unwrap();
+    let path = |name: &str| root.path().join(name);
+    fs::write(path("img_1.JPG"), "1").unwrap();
+    fs::write(path("img_2.JPG"), "2").unwrap();
+    fs::write(path("other.JPG"), "3").unwrap();
+
+    let plan = plan_rename(&format!("{}/img_(*).JPG", root.path().display()),
+                           &format!("{}/photos/(*).jpg", root.path().display()),
+                           &MatchOptions::new()).unwrap();
+    assert_eq!(plan.steps(), [
+        rename(root.path(), "img_1.JPG", "photos/1.jpg"),
+        rename(root.path(), "img_2.JPG", "photos/2.jpg"),
+    ]);
+    // nothing is renamed until applied
+    assert!(path("img_1.JPG").exists());
+    plan.apply().unwrap();
+    assert_eq!(read(path("photos/1.jpg")), "1");
+    assert_eq!(read(path("photos/2.jpg")), "2");
+    assert!(!path("img_1.JPG").exists());
+    assert!(path("other.JPG").exists());
+}
+
+#[test]
+fn chains_and_cycles() {
+    let root = TempDir::new("glob-rename").unwrap();
+    let path = |name: &str| root.path().join(name);
+    for name in &["a", "b", "c", "x"] {
+        fs::write(path(name), name).unwrap();
+    }
+    let plan = RenamePlan::new(vec![
+        rename(root.path(), "x", "y"),
+        rename(root.path(), "a", "b"),
+        rename(root.path(), "b", "c"),
+        rename(root.path(), "c", "a"),
+        rename(root.path(), "y", "y"),
+    ]).unwrap();
+    assert_eq!(plan.steps(), [
+        rename(root.path(), "x", "y"),
+        rename(root.path(), "a", "a.rename-tmp"),
+        rename(root.path(), "c", "a"),
+        rename(root.path(), "b", "c"),
+        rename(root.path(), "a.rename-tmp", "b"),
+    ]);
+    plan.apply().unwrap();
+    assert_eq!(read(path("a")), "c");
+    assert_eq!(read(path("b")), "a");
+    assert_eq!(read(path("c")), "b");
+    assert_eq!(read(path("y")), "x");
+
+    let plan = RenamePlan::new(vec![
+        rename(root.path(), "a", "b"),
+        rename(root.path(), "b", "z"),
+    ]).unwrap();
+    assert_eq!(plan.steps(), [
+        rename(root.path(), "b", "z"),
+        rename(root.path(), "a", "b"),
+    ]);
+}
+
+#[test]
+fn conflicts() {
+    let root = TempDir::new("glob-rename").unwrap();
+    let path = |name: &str| root.path().join(name);
+    fs::write(path("a"), "a").unwrap();
+    fs::write(path("b"), "b").unwrap();
+
+    match RenamePlan::new(vec![rename(root.path(), "a", "c"),
+                               rename(root.path(), "b", "c")]) {
+        Err(RenameError::Collision { target, sources }) => {
+            assert_eq!(target, path("c"));
+            assert_eq!(sources, (path("a"), path("b")));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match RenamePlan::new(vec![rename(root.path(), "a", "c"),
+                               rename(root.path(), "a", "d")]) {
+        Err(RenameError::DuplicateSource { source, targets }) => {
+            assert_eq!(source, path("a"));
+            assert_eq!(targets, (path("c"), path("d")));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    match RenamePlan::new(vec![rename(root.path(), "a", "b")]) {
+        Err(RenameError::TargetExists(target)) => assert_eq!(target, path("b")),
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn rollback() {
+    let root = TempDir::new("glob-rename").unwrap();
+    let path = |name: &str| root.path().join(name);
+    fs::write(path("a"), "a").unwrap();
+    fs::write(path("b"), "b").unwrap();
+
+    let plan = RenamePlan::new(vec![
+        rename(root.path(), "a", "x"),
+        rename(root.path(), "missing", "y"),
+        rename(root.path(), "b", "z"),
+    ]).unwrap();
+    match plan.apply() {
+        Err(RenameError::Io { rename: step, .. }) => {
+            assert_eq!(step, rename(root.path(), "missing", "y"));
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(read(path("a")), "a");
+    assert_eq!(read(path("b")), "b");
+    assert!(!path("x").exists());
+}
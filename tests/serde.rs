@@ -0,0 +1,56 @@
+#![cfg(feature = "serde")]
+
+extern crate capturing_glob as glob;
+extern crate serde_json;
+
+use glob::{MatchOptions, Pattern, Separator};
+
+#[test]
+fn pattern() {
+    let pattern: Pattern = serde_json::from_str(r#""src/(*).rs""#).unwrap();
+    assert_eq!(pattern.as_str(), "src/(*).rs");
+    assert_eq!(serde_json::to_string(&pattern).unwrap(), r#""src/(*).rs""#);
+
+    let err = serde_json::from_str::<Pattern>(r#""src/[a""#).unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.starts_with(r#"invalid glob pattern "src/[a": "#), "{}", msg);
+    assert!(msg.contains("position 4"), "{}", msg);
+    assert!(serde_json::from_str::<Pattern>("1").is_err());
+}
+
+#[test]
+fn match_options() {
+    let options: MatchOptions = serde_json::from_str("{}").unwrap();
+    assert_eq!(options, MatchOptions::new());
+
+    let options: MatchOptions = serde_json::from_str(r#"{
+        "case_sensitive": false,
+        "separator": {"char": ":"}
+    }"#).unwrap();
+    assert_eq!(options, MatchOptions {
+        case_sensitive: false,
+        separator: Separator::Char(':'),
+        .. MatchOptions::new()
+    });
+    let options: MatchOptions = serde_json::from_str(
+        r#"{"separator": "slash"}"#).unwrap();
+    assert_eq!(options.separator, Separator::Slash);
+    assert!(serde_json::from_str::<MatchOptions>(
+        r#"{"case_sensitive": "yes"}"#).is_err());
+}
+
+#[test]
+fn entry() {
+    let pattern = Pattern::new("(*)/(*).md").unwrap();
+    let entry = pattern.captures("en/index.md").unwrap();
+    assert_eq!(serde_json::to_string(&entry).unwrap(),
+        r#"{"path":"en/index.md","groups":["en","index"]}"#);
+
+    let pattern = Pattern::new("(?<lang>*)/(*).md").unwrap();
+    let entry = pattern.captures("en/index.md").unwrap();
+    assert_eq!(entry.group_name(1), Some("lang"));
+    assert_eq!(entry.group_name(2), None);
+    assert_eq!(entry.named_group("lang").unwrap(), "en");
+    assert_eq!(serde_json::to_string(&entry).unwrap(),
+        r#"{"path":"en/index.md","groups":["en","index"],"names":{"lang":"en"}}"#);
+}
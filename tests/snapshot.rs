@@ -0,0 +1,112 @@
+extern crate capturing_glob as glob;
+extern crate filetime;
+#[cfg(feature = "serde")] extern crate serde_json;
+extern crate tempdir;
+
+use std::fs;
+use std::path::Path;
+
+use filetime::FileTime;
+use glob::{diff, GlobSnapshot, MatchOptions, SnapshotEntry};
+use tempdir::TempDir;
+
+fn names(entries: &[&SnapshotEntry]) -> Vec<String> {
+    entries.iter()
+        .map(|e| e.entry().group(1).unwrap().to_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn changes() {
+    let root = TempDir::new("glob-snapshot").unwrap();
+    let path = |name: &str| root.path().join(name);
+    fs::create_dir(path("src")).unwrap();
+    fs::write(path("src/a.rs"), "a").unwrap();
+    fs::write(path("src/b.rs"), "b").unwrap();
+    fs::write(path("src/c.rs"), "c").unwrap();
+    fs::write(path("src/d.txt"), "d").unwrap();
+    // make sure the changes below update the mtime of the directory
+    filetime::set_file_mtime(path("src"),
+        FileTime::from_unix_time(1_000_000_000, 0)).unwrap();
+
+    let pattern = format!("{}/src/(*).rs", root.path().display());
+    let options = MatchOptions::new();
+    let old = GlobSnapshot::new(&pattern, &options).unwrap();
+    assert_eq!(old.entries().len(), 3);
+    assert_eq!(old.get(path("src/b.rs")).unwrap().len(), 1);
+    assert!(old.get(path("src/d.txt")).is_none());
+    assert!(diff(&old, &old).is_empty());
+
+    fs::remove_file(path("src/a.rs")).unwrap();
+    fs::write(path("src/b.rs"), "bigger").unwrap();
+    fs::write(path("src/e.rs"), "e").unwrap();
+    let new = old.update(&pattern, &options).unwrap();
+    let changes = diff(&old, &new);
+    assert_eq!(names(&changes.added), ["e"]);
+    assert_eq!(names(&changes.removed), ["a"]);
+    assert_eq!(names(&changes.changed), ["b"]);
+    assert_eq!(changes.changed[0].len(), 6);
+}
+
+#[test]
+fn unchanged_dirs_are_not_read() {
+    let root = TempDir::new("glob-snapshot").unwrap();
+    let dir = root.path().join("dir");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("a.rs"), "a").unwrap();
+    let mtime = FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(&dir, mtime).unwrap();
+
+    let pattern = format!("{}/dir/(*).rs", root.path().display());
+    let options = MatchOptions::new();
+    let old = GlobSnapshot::new(&pattern, &options).unwrap();
+
+    // pretend that the directory wasn't changed
+    fs::write(dir.join("b.rs"), "b").unwrap();
+    filetime::set_file_mtime(&dir, mtime).unwrap();
+    let new = old.update(&pattern, &options).unwrap();
+    assert!(diff(&old, &new).is_empty());
+    assert_eq!(GlobSnapshot::new(&pattern, &options).unwrap()
+               .entries().len(), 2);
+
+    filetime::set_file_mtime(&dir, FileTime::now()).unwrap();
+    let new = old.update(&pattern, &options).unwrap();
+    assert_eq!(names(&diff(&old, &new).added), ["b"]);
+}
+
+#[test]
+fn errors() {
+    let options = MatchOptions::new();
+    assert!(GlobSnapshot::new("[", &options).is_err());
+    let missing = GlobSnapshot::new("/nonexistent-dir/*", &options).unwrap();
+    assert!(missing.entries().is_empty());
+    assert!(missing.get(Path::new("/nonexistent-dir/x")).is_none());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize() {
+    let options = MatchOptions::new();
+    let old = GlobSnapshot::new("src/(*).rs", &options).unwrap();
+    let json = serde_json::to_string(&old).unwrap();
+    let copy: GlobSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy, old);
+    assert_eq!(copy.get("src/lib.rs").unwrap().entry().group(1).unwrap(),
+               "lib");
+    let new = copy.update("src/(*).rs", &options).unwrap();
+    assert!(diff(&old, &new).is_empty());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize_named_groups() {
+    let options = MatchOptions::new();
+    let old = GlobSnapshot::new("src/(?<module>*).rs", &options).unwrap();
+    let json = serde_json::to_string(&old).unwrap();
+    let copy: GlobSnapshot = serde_json::from_str(&json).unwrap();
+    assert_eq!(copy, old);
+    assert_eq!(copy.get("src/lib.rs").unwrap().entry()
+               .named_group("module").unwrap(), "lib");
+    let new = copy.update("src/(?<module>*).rs", &options).unwrap();
+    assert!(diff(&old, &new).is_empty());
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "watch", target_os = "linux"))]
+
+extern crate capturing_glob as glob;
+extern crate tempdir;
+
+use std::fs;
+use std::io::Write;
+
+use glob::{watch, MatchOptions, Pattern, Watcher, WatchEvent};
+use tempdir::TempDir;
+
+// Returns the events that are already queued, as `kind group1/group2`
+fn drain(watcher: &mut Watcher) -> Vec<String> {
+    let mut result = Vec::new();
+    while let Some(event) = watcher.try_next().unwrap() {
+        let kind = match event {
+            WatchEvent::Created(_) => "created",
+            WatchEvent::Modified(_) => "modified",
+            WatchEvent::Removed(_) => "removed",
+        };
+        let entry = event.entry();
+        result.push(format!("{} {}/{}", kind,
+            entry.group(1).unwrap().to_str().unwrap(),
+            entry.group(2).unwrap().to_str().unwrap()));
+    }
+    result
+}
+
+#[test]
+fn captures() {
+    let root = TempDir::new("glob-watch").unwrap();
+    let incoming = root.path().join("incoming");
+    fs::create_dir_all(incoming.join("a")).unwrap();
+    fs::write(incoming.join("a/old.csv"), "1").unwrap();
+    fs::write(incoming.join("a/skip.txt"), "1").unwrap();
+
+    let pattern = Pattern::new(&format!("{}/(*)/(*).csv",
+                                        incoming.display())).unwrap();
+    let mut watcher = watch(&pattern, &MatchOptions::new()).unwrap();
+    assert_eq!(drain(&mut watcher), ["created a/old"]);
+
+    fs::create_dir(incoming.join("b")).unwrap();
+    assert!(drain(&mut watcher).is_empty());
+    let mut file = fs::File::create(incoming.join("b/new.csv")).unwrap();
+    file.write_all(b"data").unwrap();
+    drop(file);
+    fs::write(incoming.join("b/new.txt"), "1").unwrap();
+    assert_eq!(drain(&mut watcher), ["created b/new", "modified b/new"]);
+
+    fs::write(incoming.join("a/old.csv"), "2").unwrap();
+    fs::remove_file(incoming.join("a/old.csv")).unwrap();
+    assert_eq!(drain(&mut watcher), ["modified a/old", "removed a/old"]);
+
+    fs::rename(incoming.join("b"), root.path().join("b")).unwrap();
+    assert_eq!(drain(&mut watcher), ["removed b/new"]);
+    fs::write(root.path().join("b/new.csv"), "2").unwrap();
+    assert!(drain(&mut watcher).is_empty());
+    fs::rename(root.path().join("b"), incoming.join("c")).unwrap();
+    assert_eq!(drain(&mut watcher), ["created c/new"]);
+}
+
+#[test]
+fn recursive() {
+    let root = TempDir::new("glob-watch").unwrap();
+    let pattern = Pattern::new(&format!("{}/(**)/(*).log",
+                                        root.path().display())).unwrap();
+    let mut watcher = watch(&pattern, &MatchOptions::new()).unwrap();
+    assert!(drain(&mut watcher).is_empty());
+
+    // the file is created before the new directories are watched
+    fs::create_dir_all(root.path().join("x/y/z")).unwrap();
+    fs::write(root.path().join("x/y/z/app.log"), "1").unwrap();
+    assert_eq!(drain(&mut watcher), ["created x/y/z/app"]);
+
+    fs::write(root.path().join("x/y/z/app.log"), "2").unwrap();
+    assert_eq!(drain(&mut watcher), ["modified x/y/z/app"]);
+    fs::remove_dir_all(root.path().join("x")).unwrap();
+    assert_eq!(drain(&mut watcher), ["removed x/y/z/app"]);
+}